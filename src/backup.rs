@@ -0,0 +1,148 @@
+use std::{fs, path::{Path, PathBuf}};
+
+use kvproto::brpb::{BackupMeta, MetaFile, Schema, File as BackupFile};
+use txn_types::Key;
+
+use crate::{errors::Error, tidbtypes::{DBInfo, TableInfo}};
+
+const BACKUP_META_FILE_NAME : &str = "backupmeta";
+
+//a data file of the backup, the keys in it are the encoded keys without the 'z' prefix.
+pub struct BackupDataFile {
+    pub path : PathBuf,
+    pub cf : String,
+    //[start_key, end_key) of the encoded keys, empty for unbounded.
+    pub start_key : Vec<u8>,
+    pub end_key : Vec<u8>,
+}
+
+//the schemas of the backup, instead of the ones in the meta keys which are not backed up.
+#[derive(Default)]
+pub struct BackupSchemas {
+    pub databases : Vec<DBInfo>,
+    //(db id, table info)
+    pub tables : Vec<(i64, TableInfo)>,
+}
+
+impl BackupSchemas {
+    //the schemas of several backups, the same databases and tables are kept once.
+    pub fn merge(&mut self, other : BackupSchemas) {
+        for db_info in other.databases {
+            if !self.databases.iter().any(|d| d.id == db_info.id) {
+                self.databases.push(db_info);
+            }
+        }
+        for (db_id, table_info) in other.tables {
+            if !self.tables.iter().any(|(_, t)| t.id == table_info.id) {
+                self.tables.push((db_id, table_info));
+            }
+        }
+    }
+}
+
+//the schemas and the data files in the backupmeta of a BR backup.
+pub struct BackupInfo {
+    pub schemas : BackupSchemas,
+    pub data_files : Vec<BackupDataFile>,
+}
+
+pub fn is_backup_dir(dir : &Path) -> bool {
+    return dir.join(BACKUP_META_FILE_NAME).is_file();
+}
+
+//the files and schemas are in the backupmeta itself, or in the meta files indexed by it since v2.
+pub fn read_backup_info(dir : &Path) -> Result<BackupInfo, Error> {
+    let meta_data = read_file(&dir.join(BACKUP_META_FILE_NAME))?;
+    let mut meta = BackupMeta::default();
+    if protobuf::Message::merge_from_bytes(&mut meta, &meta_data).is_err() {
+        return Err(Error::StorageNodeError(format!("parse the backupmeta of {} error, it may be encrypted.", dir.display())));
+    }
+    if meta.get_is_raw_kv() {
+        return Err(Error::StorageNodeError(format!("{} is a backup of raw kv, not of TiDB.", dir.display())));
+    }
+
+    let mut info = BackupInfo {
+        schemas : BackupSchemas::default(),
+        data_files : vec![],
+    };
+
+    let mut files : Vec<BackupFile> = meta.get_files().to_vec();
+    let mut schemas : Vec<Schema> = meta.get_schemas().to_vec();
+    if meta.has_file_index() {
+        read_meta_file(dir, meta.get_file_index(), &mut files, &mut schemas)?;
+    }
+    if meta.has_schema_index() {
+        read_meta_file(dir, meta.get_schema_index(), &mut files, &mut schemas)?;
+    }
+
+    for schema in schemas.iter() {
+        let db_info : DBInfo = match serde_json::from_slice(schema.get_db()) {
+            Ok(d) => d,
+            Err(_) => return Err(Error::CorruptedDataString("parse the database of the backup schema error.".to_string(), String::from_utf8_lossy(schema.get_db()).to_string())),
+        };
+        //the databases without tables have the schemas without table.
+        if !schema.get_table().is_empty() {
+            let table_info : TableInfo = match serde_json::from_slice(schema.get_table()) {
+                Ok(t) => t,
+                Err(_) => return Err(Error::CorruptedDataString("parse the table of the backup schema error.".to_string(), String::from_utf8_lossy(schema.get_table()).to_string())),
+            };
+            info.schemas.tables.push((db_info.id, table_info));
+        }
+        if !info.schemas.databases.iter().any(|d| d.id == db_info.id) {
+            info.schemas.databases.push(db_info);
+        }
+    }
+
+    for file in files {
+        if !file.get_cipher_iv().is_empty() {
+            return Err(Error::StorageNodeError(format!("the backup file {} is encrypted.", file.get_name())));
+        }
+        let cf = if file.get_cf().is_empty() {
+            "default".to_string()
+        } else {
+            file.get_cf().to_string()
+        };
+        info.data_files.push(BackupDataFile {
+            path : dir.join(file.get_name()),
+            cf,
+            //the ranges of the files are raw keys.
+            start_key : encode_range_key(file.get_start_key()),
+            end_key : encode_range_key(file.get_end_key()),
+        });
+    }
+
+    return Ok(info);
+}
+
+//the meta files are organized as a tree, the leaves have the data files and the schemas.
+fn read_meta_file(dir : &Path, meta_file : &MetaFile, files : &mut Vec<BackupFile>, schemas : &mut Vec<Schema>) -> Result<(), Error> {
+    files.extend_from_slice(meta_file.get_data_files());
+    schemas.extend_from_slice(meta_file.get_schemas());
+
+    for child in meta_file.get_meta_files() {
+        if !child.get_cipher_iv().is_empty() {
+            return Err(Error::StorageNodeError(format!("the backup meta file {} is encrypted.", child.get_name())));
+        }
+        let child_data = read_file(&dir.join(child.get_name()))?;
+        let mut child_meta_file = MetaFile::default();
+        if protobuf::Message::merge_from_bytes(&mut child_meta_file, &child_data).is_err() {
+            return Err(Error::StorageNodeError(format!("parse the backup meta file {} error.", child.get_name())));
+        }
+        read_meta_file(dir, &child_meta_file, files, schemas)?;
+    }
+    return Ok(());
+}
+
+fn read_file(path : &Path) -> Result<Vec<u8>, Error> {
+    return match fs::read(path) {
+        Ok(d) => Ok(d),
+        Err(e) => Err(Error::IO(format!("read {} error, {}", path.display(), e.to_string()))),
+    };
+}
+
+fn encode_range_key(raw_key : &[u8]) -> Vec<u8> {
+    if raw_key.is_empty() {
+        return vec![];
+    }
+    return Key::from_raw(raw_key).into_encoded();
+}
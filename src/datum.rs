@@ -2,49 +2,62 @@
 use std::marker::PhantomData;
 use std::cmp::Ordering::{Equal, Greater, Less};
 use num_traits::PrimInt;
-use tidb_query_datatype::codec::table::decode_int_handle;
+use tidb_query_datatype::codec::table::{decode_int_handle, decode_common_handle};
+use tidb_query_datatype::codec::datum::{self, Datum};
 use tidb_query_datatype::expr::EvalContext;
 
-use codec::prelude::{NumberDecoder, BufferReader};
+use codec::prelude::{NumberDecoder, NumberEncoder, BufferReader};
 use tidb_query_datatype::codec::data_type::JsonRef;
 use tidb_query_datatype::codec::datum_codec::DatumPayloadDecoder;
-use tidb_query_datatype::codec::mysql::{JsonType, Decimal, Duration, Time, TimeType};
+use tidb_query_datatype::codec::mysql::{Json, JsonType, Decimal, DecimalEncoder, Duration, Time, TimeType};
+use tidb_query_datatype::codec::mysql::decimal::{Res, RoundMode};
+use tidb_query_datatype::codec::mysql::json::JsonEncoder;
 use tidb_query_datatype::{codec::row::v2::*, FieldTypeTp};
 use codec::number::NumberCodec;
 use crate::errors::Error;
-use crate::tidbtypes::{TableInfo, ColumnInfo};
-use txn_types::{Key, TimeStamp};
+use crate::tidbtypes::{TableInfo, ColumnInfo, TableInfoVersion};
+use txn_types::TimeStamp;
 
 
 //some code are copyed from tikv RowSlice
 pub struct RowData {
-    pub handle_int : i64,
     pub append_ts : TimeStamp,
     key_data : Box<[u8]>,
     val_data : Box<[u8]>,
-    pri_data : Box<[u8]>,
+    //the columns not stored in val_data, such as primary key columns restored from the handle.
+    //(column id, value encoded as row format v2, None for null)
+    restored_data : Vec<(i64, Option<Box<[u8]>>)>,
 }
 
 impl RowData {
-    pub fn new(key_data : Box<[u8]>, val_data : Box<[u8]>, table_info : &TableInfo) -> Result<RowData, Error> {
-        
-        let key = Key::from_raw(key_data.as_ref());
-        let handle_int = match decode_int_handle(key_data.as_ref()) {
-            Ok(handle) =>handle,
-            Err(e) => return Err(Error::CorruptedDataBytes("decode the 'handle' error in the new method of RowData. ".to_string(), key_data)),
+    pub fn new(key_data : Box<[u8]>, val_data : Box<[u8]>, append_ts : TimeStamp, table_info : &TableInfo) -> Result<RowData, Error> {
+        //rows written by old versions of TiDB, convert them to format v2.
+        let val_data = if is_row_format_v2(val_data.as_ref()) {
+            val_data
+        } else {
+            convert_row_v1_to_v2(val_data.as_ref())?.into_boxed_slice()
         };
+
         let mut row_data = RowData {
-            handle_int : handle_int,
-            append_ts : key.decode_ts().unwrap(),
+            append_ts : append_ts,
             key_data: key_data,
             val_data: val_data,
-            pri_data: Box::new([0; 8]),
+            restored_data: Vec::new(),
         };
 
-        if table_info.pk_is_handle {
-            for col in &table_info.cols {
-                if col.field_type.has_prikey_flag() {
-                    row_data.write_pri_data(col);
+        if table_info.is_common_handle {
+            row_data.write_common_handle_data(table_info)?;
+        } else {
+            let handle_int = match decode_int_handle(row_data.key_data.as_ref()) {
+                Ok(handle) =>handle,
+                Err(e) => return Err(Error::CorruptedDataBytes("decode the 'handle' error in the new method of RowData. ".to_string(), row_data.key_data)),
+            };
+
+            if table_info.pk_is_handle {
+                for col in &table_info.cols {
+                    if col.field_type.has_prikey_flag() {
+                        row_data.write_pri_data(col, handle_int);
+                    }
                 }
             }
         }
@@ -52,10 +65,32 @@ impl RowData {
         return Ok(row_data);
     }
 
+    //the raw record key, t{table_id}_r{handle}.
+    pub fn get_key(&self) -> &[u8] {
+        return &self.key_data;
+    }
+
+    //the row value in format v2, the ones in format v1 are converted.
+    pub fn get_value(&self) -> &[u8] {
+        return &self.val_data;
+    }
+
+    //the encoded handle, int handle and common handle are both memcomparable.
+    pub fn get_handle(&self) -> &[u8] {
+        return &self.key_data[RECORD_KEY_PREFIX_LEN..];
+    }
+
     pub fn get_datum_refs<'a, 'b> (&'b self, table_info : &'a TableInfo) -> Result<Vec<DatumRef<'b, 'a>>, Error> {
+        return self.get_datum_refs_of_cols(table_info.cols.iter());
+    }
+
+    //the datum refs of the columns in the given order, the other columns are not located.
+    pub fn get_datum_refs_of_cols<'a, 'b, I : ExactSizeIterator<Item = &'a ColumnInfo>> (&'b self, cols : I) -> Result<Vec<DatumRef<'b, 'a>>, Error> {
         let mut data = self.val_data.as_ref();
         if let Ok(v) = data.read_u8() {
-            assert_eq!(v, CODEC_VERSION);
+            if v != CODEC_VERSION {
+                return Err(Error::CorruptedDataBytes(format!("unknown row format version {} in the get_datum_refs method", v), self.val_data.clone()));
+            }
         } else {
             return Err(Error::CorruptedDataBytes("get CODEC_VERSION error in the get_datum_refs method".to_string(), self.val_data.clone()));
         }
@@ -66,21 +101,132 @@ impl RowData {
         } & 1 == 1;
 
         if is_big {
-            return self.get_datum_refs_as_big(data, table_info);
+            return self.get_datum_refs_as_big(data, cols);
         } else {
-            return self.get_datum_refs_as_small(data, table_info);
+            return self.get_datum_refs_as_small(data, cols);
         }
     }
 
-    fn write_pri_data(& mut self, col : &ColumnInfo) {
-        if col.field_type.is_unsigned() {
-            self.pri_data.copy_from_slice(&(self.handle_int as u64).to_le_bytes());
+    fn write_pri_data(& mut self, col : &ColumnInfo, handle_int : i64) {
+        let pri_data : Box<[u8]> = if col.field_type.is_unsigned() {
+            Box::new((handle_int as u64).to_le_bytes())
         } else {
-            self.pri_data.copy_from_slice(&(self.handle_int as i64).to_le_bytes());
+            Box::new(handle_int.to_le_bytes())
+        };
+        self.restored_data.push((col.id, Some(pri_data)));
+    }
+
+    fn write_common_handle_data(&mut self, table_info : &TableInfo) -> Result<(), Error> {
+        let handle_cols = table_info.get_common_handle_cols();
+        let mut handle_data = self.get_handle();
+        let datums = match datum::decode(&mut handle_data) {
+            Ok(d) => d,
+            Err(_) => return Err(Error::CorruptedDataBytes("decode the common handle error in the new method of RowData.".to_string(), self.key_data.clone())),
+        };
+
+        if datums.len() != handle_cols.len() {
+            return Err(Error::CorruptedDataBytes(
+                format!("the common handle has {} columns, but the primary key has {}.", datums.len(), handle_cols.len()),
+                self.key_data.clone()
+            ));
+        }
+
+        for (col, d) in handle_cols.iter().zip(datums.iter()) {
+            if let Some(col_data) = encode_datum_to_v2_bytes(d)? {
+                self.restored_data.push((col.id, Some(col_data.into_boxed_slice())));
+            }
+        }
+
+        return Ok(());
+    }
+
+    //the row is written by an older schema version of the table, the columns changed by a lossy
+    //MODIFY COLUMN have new ids, so their values are read from the old columns of the same names
+    //and converted to the new types.
+    //row_schema_versions are the version of the row and the later ones. a column dropped and added
+    //again also has the same name, it's told apart by the versions without the name between them.
+    pub fn restore_changed_columns(&mut self, table_info : &TableInfo, row_schema_versions : &[TableInfoVersion]) -> Result<(), Error> {
+        let row_table_info = match row_schema_versions.first() {
+            Some(v) => &v.table_info,
+            None => return Ok(()),
+        };
+        let row = match RowSlice::from_bytes(self.val_data.as_ref()) {
+            Ok(r) => r,
+            Err(_) => return Err(Error::CorruptedDataBytes("parse row format v2 error.".to_string(), self.val_data.clone())),
+        };
+
+        let mut restored_data = vec![];
+        for col in table_info.cols.iter() {
+            if self.get_restored_data(col.id).is_some() || is_column_in_row(&row, col.id)? {
+                continue;
+            }
+
+            let old_col = match row_table_info.cols.iter().find(|c| c.id != col.id && c.name.L == col.name.L) {
+                Some(c) => c,
+                None => continue,
+            };
+            let is_name_kept = row_schema_versions.iter()
+                .all(|v| v.table_info.cols.iter().any(|c| c.name.L == col.name.L));
+            if !is_name_kept {
+                continue;
+            }
+
+            let old_data = match row.search_in_non_null_ids(old_col.id) {
+                Ok(Some((start, offset))) => &row.values()[start..offset],
+                Ok(None) => {
+                    if row.search_in_null_ids(old_col.id) {
+                        restored_data.push((col.id, None));
+                    }
+                    continue;
+                },
+                Err(_) => return Err(Error::CorruptedDataBytes(format!("search column {} in the row error.", old_col.id), self.val_data.clone())),
+            };
+
+            let d = DatumRef::parse_from(old_data, old_col).to_datum()?;
+            let col_data = convert_datum_for_column(d, col)?;
+            restored_data.push((col.id, col_data.map(|data| data.into_boxed_slice())));
+        }
+
+        self.restored_data.extend(restored_data);
+        return Ok(());
+    }
+
+    //the columns missing in the row are added after the row is written, they are filled with
+    //the origin default values.
+    pub fn restore_origin_defaults(&mut self, origin_defaults : &[OriginDefault]) -> Result<(), Error> {
+        if origin_defaults.is_empty() {
+            return Ok(());
         }
+
+        let row = match RowSlice::from_bytes(self.val_data.as_ref()) {
+            Ok(r) => r,
+            Err(_) => return Err(Error::CorruptedDataBytes("parse row format v2 error.".to_string(), self.val_data.clone())),
+        };
+
+        let mut restored_data = vec![];
+        for (col_id, default_data) in origin_defaults {
+            if self.get_restored_data(*col_id).is_some() || is_column_in_row(&row, *col_id)? {
+                continue;
+            }
+            //the invalid origin default only fails the rows which need it.
+            match default_data {
+                Ok(d) => restored_data.push((*col_id, d.clone())),
+                Err(e) => return Err(Error::Other(format!("the origin default of column {} is invalid, {}", col_id, e))),
+            }
+        }
+
+        self.restored_data.extend(restored_data);
+        return Ok(());
+    }
+
+    //Some(None) for the restored null value.
+    fn get_restored_data(&self, col_id : i64) -> Option<Option<&[u8]>> {
+        return self.restored_data.iter()
+            .find(|(id, _)| *id == col_id)
+            .map(|(_, d)| d.as_deref());
     }
 
-    fn get_datum_refs_as_small<'a, 'b> (&'b self, mut data : &'b [u8], table_info : &'a TableInfo) -> Result<Vec<DatumRef<'b, 'a>>, Error> {
+    fn get_datum_refs_as_small<'a, 'b, I : ExactSizeIterator<Item = &'a ColumnInfo>> (&'b self, mut data : &'b [u8], cols : I) -> Result<Vec<DatumRef<'b, 'a>>, Error> {
         let origin_data = data.clone();
 
         let non_null_cnt = match data.read_u16_le() {
@@ -97,7 +243,6 @@ impl RowData {
         let offsets : LeBytes<'b, u16> = read_le_bytes(&mut data, non_null_cnt)?;
         let values : LeBytes<'b, u8> = LeBytes::new(data);
 
-        let cols = &table_info.cols;
         let mut datum_list = Vec::with_capacity(cols.len());
 
         for col in cols {
@@ -118,10 +263,10 @@ impl RowData {
             } else if null_ids.binary_search(&(col.id as u8)).is_ok() {
                 datum_list.push(DatumRef::get_null(col));
             } else {
-                // This column is missing. It will be filled with default values
-                // later.
-                if table_info.pk_is_handle && col.field_type.has_prikey_flag() {
-                    let datum_ref = DatumRef::parse_from(self.pri_data.as_ref(), col);
+                // This column is missing. It will be filled with the data restored from
+                // the handle or null.
+                if let Some(Some(restored_data)) = self.get_restored_data(col.id) {
+                    let datum_ref = DatumRef::parse_from(restored_data, col);
                     datum_list.push(datum_ref);
                 } else {
                     datum_list.push(DatumRef::get_null(col));
@@ -131,7 +276,7 @@ impl RowData {
         return Ok(datum_list);
     }
 
-    fn get_datum_refs_as_big <'a, 'b> (&'b self, mut data : &'b [u8], table_info : &'a TableInfo) -> Result<Vec<DatumRef<'b, 'a>>, Error> {
+    fn get_datum_refs_as_big <'a, 'b, I : ExactSizeIterator<Item = &'a ColumnInfo>> (&'b self, mut data : &'b [u8], cols : I) -> Result<Vec<DatumRef<'b, 'a>>, Error> {
         let origin_data = data.clone();
         let non_null_cnt = match data.read_u16_le() {
             Ok(nnc) => nnc,
@@ -147,7 +292,6 @@ impl RowData {
         let offsets : LeBytes<'b, u32> = read_le_bytes(&mut data, non_null_cnt)?;
         let values : LeBytes<'b, u8> = LeBytes::new(data);
 
-        let cols = &table_info.cols;
         let mut datum_list = Vec::with_capacity(cols.len());
 
         for col in cols {
@@ -168,10 +312,10 @@ impl RowData {
             } else if null_ids.binary_search(&(col.id as u32)).is_ok() {
                 datum_list.push(DatumRef::get_null(col));
             } else {
-                // This column is missing. It will be filled with default values
-                // later.
-                if table_info.pk_is_handle && col.field_type.has_prikey_flag() {
-                    let datum_ref = DatumRef::parse_from(self.pri_data.as_ref(), col);
+                // This column is missing. It will be filled with the data restored from
+                // the handle or null.
+                if let Some(Some(restored_data)) = self.get_restored_data(col.id) {
+                    let datum_ref = DatumRef::parse_from(restored_data, col);
                     datum_list.push(datum_ref);
                 } else {
                     datum_list.push(DatumRef::get_null(col));
@@ -182,6 +326,333 @@ impl RowData {
     }
 }
 
+//t{table_id}_r
+const RECORD_KEY_PREFIX_LEN : usize = tidb_query_datatype::codec::table::PREFIX_LEN;
+
+//the encoded handle data of a raw record key.
+pub fn decode_handle_data(key_data : &[u8]) -> Result<&[u8], Error> {
+    return match decode_common_handle(key_data) {
+        Ok(handle_data) => Ok(handle_data),
+        Err(_) => Err(Error::CorruptedDataBytes("decode handle data from the record key error.".to_string(), Box::from(key_data))),
+    };
+}
+
+//encode the datum decoded from keys as the column value of row format v2, None for null.
+pub fn encode_datum_to_v2_bytes(d : &Datum) -> Result<Option<Vec<u8>>, Error> {
+    let mut buf : Vec<u8> = Vec::new();
+    match d {
+        Datum::Null => return Ok(None),
+        Datum::I64(i) => buf.extend_from_slice(&i.to_le_bytes()),
+        Datum::U64(u) => buf.extend_from_slice(&u.to_le_bytes()),
+        Datum::F64(f) => {
+            if buf.write_f64(*f).is_err() {
+                return Err(Error::Other("encode f64 datum error.".to_string()));
+            }
+        },
+        Datum::Dur(dur) => buf.extend_from_slice(&dur.to_nanos().to_le_bytes()),
+        Datum::Bytes(b) => buf.extend_from_slice(b),
+        Datum::Dec(dec) => {
+            let (prec, frac) = dec.prec_and_frac();
+            if buf.write_decimal(dec, prec, frac).is_err() {
+                return Err(Error::Other(format!("encode decimal datum error, {}.", dec)));
+            }
+        },
+        Datum::Time(t) => {
+            let packed = match t.to_packed_u64(&mut EvalContext::default()) {
+                Ok(p) => p,
+                Err(_) => return Err(Error::Other(format!("encode time datum error, {}.", t))),
+            };
+            buf.extend_from_slice(&packed.to_le_bytes());
+        },
+        Datum::Json(j) => {
+            if buf.write_json(j.as_ref()).is_err() {
+                return Err(Error::Other("encode json datum error.".to_string()));
+            }
+        },
+        Datum::Enum(e) => buf.extend_from_slice(&e.value().to_le_bytes()),
+        Datum::Set(set) => buf.extend_from_slice(&set.value().to_le_bytes()),
+        _ => return Err(Error::Other(format!("unsupported datum {:?}.", d))),
+    }
+
+    return Ok(Some(buf));
+}
+
+//the value of a column in the row, encoded as row format v2, None for null or missing.
+pub fn get_row_column_data(val_data : &[u8], col_id : i64) -> Result<Option<Vec<u8>>, Error> {
+    let converted_data;
+    let val_data = if is_row_format_v2(val_data) {
+        val_data
+    } else {
+        converted_data = convert_row_v1_to_v2(val_data)?;
+        converted_data.as_slice()
+    };
+
+    let row = match RowSlice::from_bytes(val_data) {
+        Ok(r) => r,
+        Err(_) => return Err(Error::CorruptedDataBytes("parse row format v2 error.".to_string(), Box::from(val_data))),
+    };
+    return match row.search_in_non_null_ids(col_id) {
+        Ok(Some((start, offset))) => Ok(Some(row.values()[start..offset].to_vec())),
+        Ok(None) => Ok(None),
+        Err(_) => Err(Error::CorruptedDataBytes(format!("search column {} in the row error.", col_id), Box::from(val_data))),
+    };
+}
+
+fn is_column_in_row(row : &RowSlice, col_id : i64) -> Result<bool, Error> {
+    return match row.search_in_non_null_ids(col_id) {
+        Ok(Some(_)) => Ok(true),
+        Ok(None) => Ok(row.search_in_null_ids(col_id)),
+        Err(_) => Err(Error::CorruptedData(format!("search column {} in the row error.", col_id))),
+    };
+}
+
+//convert the datum to the type of the column, encoded as the column value of row format v2.
+pub fn convert_datum_for_column(d : Datum, col : &ColumnInfo) -> Result<Option<Vec<u8>>, Error> {
+    if let Datum::Null = d {
+        return Ok(None);
+    }
+
+    let field_type = &col.field_type;
+    let mut ctx = EvalContext::default();
+    let converted = match FieldTypeTp::from_u8(field_type.Tp).unwrap_or(FieldTypeTp::Unspecified) {
+        FieldTypeTp::Tiny
+        | FieldTypeTp::Short
+        | FieldTypeTp::Int24
+        | FieldTypeTp::Long
+        | FieldTypeTp::LongLong => {
+            if field_type.is_unsigned() {
+                let u = match d {
+                    Datum::U64(u) => u,
+                    d => {
+                        //the negative values can't be stored in the unsigned columns.
+                        let i = d.into_i64(&mut ctx).map_err(|_| convert_err(col))?;
+                        if i < 0 {
+                            return Err(convert_err(col));
+                        }
+                        i as u64
+                    },
+                };
+                Datum::U64(u)
+            } else {
+                Datum::I64(d.into_i64(&mut ctx).map_err(|_| convert_err(col))?)
+            }
+        },
+        FieldTypeTp::Float
+        | FieldTypeTp::Double => Datum::F64(d.into_f64(&mut ctx).map_err(|_| convert_err(col))?),
+        FieldTypeTp::NewDecimal => {
+            let dec = d.into_dec().map_err(|_| convert_err(col))?;
+            let dec = match dec.round(field_type.Decimal as i8, RoundMode::HalfEven) {
+                Res::Ok(d) | Res::Truncated(d) => d,
+                Res::Overflow(_) => return Err(convert_err(col)),
+            };
+            //the integer digits out of the column are clamped to its max or min value like the cast of TiDB.
+            let (prec, frac) = dec.prec_and_frac();
+            if !dec.is_zero() && prec as i64 - frac as i64 > field_type.Flen as i64 - field_type.Decimal as i64 {
+                Datum::Dec(get_max_or_min_dec(dec.is_negative(), col)?)
+            } else {
+                Datum::Dec(dec)
+            }
+        },
+        FieldTypeTp::VarChar
+        | FieldTypeTp::VarString
+        | FieldTypeTp::String
+        | FieldTypeTp::TinyBlob
+        | FieldTypeTp::MediumBlob
+        | FieldTypeTp::LongBlob
+        | FieldTypeTp::Blob => {
+            let mut s = d.into_string().map_err(|_| convert_err(col))?;
+            //the length of char and varchar is measured in characters.
+            if field_type.Flen as i32 > 0 {
+                if let Some((idx, _)) = s.char_indices().nth(field_type.Flen as usize) {
+                    s.truncate(idx);
+                }
+            }
+            Datum::Bytes(s.into_bytes())
+        },
+        FieldTypeTp::Enum => {
+            let s = d.into_string().map_err(|_| convert_err(col))?;
+            match field_type.Elems.iter().position(|e| e.eq(&s)) {
+                Some(idx) => Datum::U64(idx as u64 + 1),
+                None => return Err(convert_err(col)),
+            }
+        },
+        //the values of the following types are only converted from the strings, such as the default values.
+        FieldTypeTp::Set => match d {
+            Datum::Bytes(b) => {
+                let s = String::from_utf8(b).map_err(|_| convert_err(col))?;
+                let mut set_val : u64 = 0;
+                for item in s.split(',').filter(|item| !item.is_empty()) {
+                    match field_type.Elems.iter().position(|e| e.eq(item)) {
+                        Some(idx) => set_val |= 1 << idx,
+                        None => return Err(convert_err(col)),
+                    }
+                }
+                Datum::U64(set_val)
+            },
+            _ => d,
+        },
+        FieldTypeTp::Year => match d {
+            Datum::Bytes(_) => Datum::I64(d.into_i64(&mut ctx).map_err(|_| convert_err(col))?),
+            _ => d,
+        },
+        FieldTypeTp::Date
+        | FieldTypeTp::DateTime
+        | FieldTypeTp::Timestamp => match d {
+            Datum::Bytes(b) => {
+                let s = String::from_utf8(b).map_err(|_| convert_err(col))?;
+                let time_type = match FieldTypeTp::from_u8(field_type.Tp) {
+                    Some(FieldTypeTp::Date) => TimeType::Date,
+                    Some(FieldTypeTp::Timestamp) => TimeType::Timestamp,
+                    _ => TimeType::DateTime,
+                };
+                let t = Time::parse(&mut ctx, &s, time_type, field_type.Decimal as i8, false).map_err(|_| convert_err(col))?;
+                Datum::Time(t)
+            },
+            _ => d,
+        },
+        FieldTypeTp::Duration => match d {
+            Datum::Bytes(b) => {
+                let s = String::from_utf8(b).map_err(|_| convert_err(col))?;
+                Datum::Dur(Duration::parse(&mut ctx, &s, field_type.Decimal as i8).map_err(|_| convert_err(col))?)
+            },
+            _ => d,
+        },
+        FieldTypeTp::Json => match d {
+            Datum::Bytes(b) => {
+                let s = String::from_utf8(b).map_err(|_| convert_err(col))?;
+                Datum::Json(s.parse::<Json>().map_err(|_| convert_err(col))?)
+            },
+            _ => d,
+        },
+        _ => d,
+    };
+
+    return encode_datum_to_v2_bytes(&converted);
+}
+
+//the max or min value of the decimal column, such as 999.99 for DECIMAL(5,2).
+fn get_max_or_min_dec(is_negative : bool, col : &ColumnInfo) -> Result<Decimal, Error> {
+    let frac = col.field_type.Decimal.max(0) as usize;
+    let int_digits = (col.field_type.Flen as usize).saturating_sub(frac);
+    let mut s = if is_negative { "-".to_string() } else { String::new() };
+    s.push_str(&if int_digits > 0 { "9".repeat(int_digits) } else { "0".to_string() });
+    if frac > 0 {
+        s.push('.');
+        s.push_str(&"9".repeat(frac));
+    }
+    return Datum::Bytes(s.into_bytes()).into_dec().map_err(|_| convert_err(col));
+}
+
+fn convert_err(col : &ColumnInfo) -> Error {
+    return Error::Other(format!("convert the value to the type of column {} error.", col.name.O));
+}
+
+//(column id, origin default value encoded as row format v2 or the error of converting it)
+pub type OriginDefault = (i64, Result<Option<Box<[u8]>>, String>);
+
+//the origin default values of the columns, encoded as the column values of row format v2.
+//the columns without origin default values are null for the rows written before them.
+pub fn get_origin_defaults(table_info : &TableInfo) -> Vec<OriginDefault> {
+    let mut ret = vec![];
+    for col in table_info.cols.iter() {
+        let tp = FieldTypeTp::from_u8(col.field_type.Tp).unwrap_or(FieldTypeTp::Unspecified);
+        let default_data = if tp == FieldTypeTp::Bit {
+            //bit values are big endian bytes, stored as u64 in row format v2.
+            match col.get_origin_default_bit() {
+                Some(bits) => Ok(Some(bits.iter().fold(0u64, |v, b| (v << 8) | *b as u64).to_le_bytes().to_vec())),
+                None => continue,
+            }
+        } else {
+            match col.get_origin_default() {
+                Some(s) => convert_datum_for_column(Datum::Bytes(s.into_bytes()), col).map_err(|e| e.to_string()),
+                None => continue,
+            }
+        };
+        ret.push((col.id, default_data.map(|d| d.map(|data| data.into_boxed_slice()))));
+    }
+    return ret;
+}
+
+pub fn is_row_format_v2(val_data : &[u8]) -> bool {
+    return val_data.first() == Some(&CODEC_VERSION);
+}
+
+//row format v1 is a list of (column id, value) datum pairs.
+pub fn convert_row_v1_to_v2(val_data : &[u8]) -> Result<Vec<u8>, Error> {
+    let mut data = val_data;
+    let datums = match datum::decode(&mut data) {
+        Ok(d) => d,
+        Err(_) => return Err(Error::CorruptedDataBytes("decode row format v1 datums error.".to_string(), Box::from(val_data))),
+    };
+
+    //a row without any column is encoded as a single null datum.
+    if datums.len() == 1 && matches!(datums[0], Datum::Null) {
+        return Ok(encode_row_v2(Vec::new()));
+    }
+
+    if datums.len() % 2 != 0 {
+        return Err(Error::CorruptedDataBytes("row format v1 datums are not paired.".to_string(), Box::from(val_data)));
+    }
+
+    let mut cols : Vec<(i64, Option<Vec<u8>>)> = Vec::with_capacity(datums.len() / 2);
+    for pair in datums.chunks(2) {
+        let col_id = match pair[0] {
+            Datum::I64(id) => id,
+            Datum::U64(id) => id as i64,
+            _ => return Err(Error::CorruptedDataBytes("invalid column id of row format v1.".to_string(), Box::from(val_data))),
+        };
+        cols.push((col_id, encode_datum_to_v2_bytes(&pair[1])?));
+    }
+
+    return Ok(encode_row_v2(cols));
+}
+
+//build a row of format v2 from (column id, value encoded as row format v2, None for null).
+pub fn encode_row_v2(mut cols : Vec<(i64, Option<Vec<u8>>)>) -> Vec<u8> {
+    cols.sort_by_key(|(id, _)| *id);
+
+    let mut non_null_ids : Vec<i64> = Vec::with_capacity(cols.len());
+    let mut null_ids : Vec<i64> = Vec::new();
+    let mut offsets : Vec<usize> = Vec::with_capacity(cols.len());
+    let mut values : Vec<u8> = Vec::new();
+    for (id, val) in cols {
+        match val {
+            Some(v) => {
+                non_null_ids.push(id);
+                values.extend_from_slice(&v);
+                offsets.push(values.len());
+            },
+            None => null_ids.push(id),
+        }
+    }
+
+    let is_big = values.len() > u16::MAX as usize
+        || non_null_ids.iter().chain(null_ids.iter()).any(|id| *id > u8::MAX as i64);
+
+    let mut buf : Vec<u8> = Vec::with_capacity(6 + (non_null_ids.len() + null_ids.len()) * 4 + offsets.len() * 4 + values.len());
+    buf.push(CODEC_VERSION);
+    buf.push(if is_big { 1 } else { 0 });
+    buf.extend_from_slice(&(non_null_ids.len() as u16).to_le_bytes());
+    buf.extend_from_slice(&(null_ids.len() as u16).to_le_bytes());
+    for id in non_null_ids.iter().chain(null_ids.iter()) {
+        if is_big {
+            buf.extend_from_slice(&(*id as u32).to_le_bytes());
+        } else {
+            buf.push(*id as u8);
+        }
+    }
+    for offset in offsets {
+        if is_big {
+            buf.extend_from_slice(&(offset as u32).to_le_bytes());
+        } else {
+            buf.extend_from_slice(&(offset as u16).to_le_bytes());
+        }
+    }
+    buf.extend_from_slice(&values);
+
+    return buf;
+}
+
 pub fn parse_datum_refs<'a, 'b>(row_slice : &'a RowSlice, table_info : &'b TableInfo) -> Result<Vec<DatumRef<'a, 'b>>, Error> {
     let cols = &table_info.cols;
     let mut datum_list = Vec::with_capacity(cols.len());
@@ -227,6 +698,40 @@ impl <'a, 'b> DatumRef<'a, 'b> {
         return self.col;
     }
 
+    //the owned datum of the value, for converting it to another type.
+    pub fn to_datum(&self) -> Result<Datum, Error> {
+        if self.is_null() {
+            return Ok(Datum::Null);
+        } else if self.is_integer() {
+            if self.col.field_type.is_unsigned() {
+                return Ok(Datum::U64(self.as_u64()?));
+            }
+            return Ok(Datum::I64(self.as_i64()?));
+        } else if self.is_float() {
+            return Ok(Datum::F64(self.as_f32()? as f64));
+        } else if self.is_double() {
+            return Ok(Datum::F64(self.as_double()?));
+        } else if self.is_decimal() {
+            return Ok(Datum::Dec(self.as_decimal()?));
+        } else if self.is_string() {
+            return Ok(Datum::Bytes(self.data.to_vec()));
+        } else if self.is_duration() {
+            return Ok(Datum::Dur(self.as_duration()?));
+        } else if self.is_timestamp() {
+            return Ok(Datum::Time(self.as_timestamp()?));
+        } else if self.is_datatime() {
+            return Ok(Datum::Time(self.as_datetime()?));
+        } else if self.is_json() {
+            return Ok(Datum::Json(self.as_json_ref()?.to_owned()));
+        } else if self.is_bit() {
+            return Ok(Datum::U64(self.as_bit()?));
+        } else if self.is_year() {
+            return Ok(Datum::I64(self.as_year()?));
+        }
+
+        return Ok(Datum::Bytes(self.try_to_string()?.into_bytes()));
+    }
+
     pub fn as_u64(&self) -> Result<u64, Error> {
         if !self.is_integer() || !self.col.field_type.is_unsigned() {
             return Err(Error::CorruptedData("invalid u64 data".to_string()));
@@ -242,16 +747,7 @@ impl <'a, 'b> DatumRef<'a, 'b> {
             return Err(Error::CorruptedData("invalid i64 data".to_string()));
         }
 
-        return match self.data.len() {
-            1 => Ok(i64::from(self.data[0] as i8)),
-            2 => Ok(i64::from(NumberCodec::decode_u16_le(self.data) as i16)),
-            4 => Ok(i64::from(NumberCodec::decode_u32_le(self.data) as i32)),
-            8 => Ok(NumberCodec::decode_u64_le(self.data) as i64),
-            _ => Err(Error::CorruptedDataBytes(
-                "Failed to decode row v2 data as i64".to_owned(),
-                Box::from(self.data)
-            )),
-        }
+        return decode_v2_i64(self.data);
     }
 
     pub fn as_f32(&self) -> Result<f32, Error> {
@@ -357,7 +853,7 @@ impl <'a, 'b> DatumRef<'a, 'b> {
     }
 
     fn as_enum_val(&self) ->Result<String, Error> {
-        if self.is_enum() {
+        if !self.is_enum() {
             return Err(Error::CorruptedData("invalid enum data".to_string()));
         }
 
@@ -372,7 +868,7 @@ impl <'a, 'b> DatumRef<'a, 'b> {
     }
 
     fn as_set_vals(&self) -> Result<Vec<String>, Error> {
-        if self.is_enum() {
+        if !self.is_set() {
             return Err(Error::CorruptedData("invalid set data".to_string()));
         }
         let mut res = Vec::<String>::new();
@@ -391,12 +887,28 @@ impl <'a, 'b> DatumRef<'a, 'b> {
         return Ok(res);
     }
 
+    //the bits are stored as u64.
+    pub fn as_bit(&self) -> Result<u64, Error> {
+        if !self.is_bit() {
+            return Err(Error::CorruptedData("invalid bit data".to_string()));
+        }
+        return decode_v2_u64(self.data).or(Err(Error::CorruptedDataBytes("invalid bit data".to_string(), Box::from(self.data))));
+    }
+
+    pub fn as_year(&self) -> Result<i64, Error> {
+        if !self.is_year() {
+            return Err(Error::CorruptedData("invalid year data".to_string()));
+        }
+        return decode_v2_i64(self.data);
+    }
+
     pub fn as_duration(&self) -> Result<Duration, Error> {
         if !self.is_duration() {
             return Err(Error::CorruptedData("invalid duration data".to_string()));
         }
 
-        let nanos = self.as_i64()?;
+        //the nanoseconds are encoded as the variable length i64 like the integers.
+        let nanos = decode_v2_i64(self.data)?;
         let fsp = self.col.field_type.Decimal as i8;
         let d = Duration::from_nanos(nanos, fsp).or(Err(Error::CorruptedDataBytes("invalid duration data".to_string(), Box::from(self.data))))?;
         return Ok(d);
@@ -553,6 +1065,11 @@ impl <'a, 'b> DatumRef<'a, 'b> {
             return Ok(self.as_timestamp()?.to_string());
         } else if self.is_datatime() {
             return Ok(self.as_datetime()?.to_string());
+        } else if self.is_bit() {
+            return Ok(self.as_bit()?.to_string());
+        } else if self.is_year() {
+            //the zero year is displayed as 0000.
+            return Ok(format!("{:04}", self.as_year()?));
         } else if self.tp == FieldTypeTp::Null {
             return Ok("NULL".to_string());
         }
@@ -569,6 +1086,20 @@ impl ToString for DatumRef<'_, '_> {
 
 #[cfg(target_endian = "little")]
 #[inline]
+//the i64 of row format v2 is stored in 1, 2, 4 or 8 bytes.
+fn decode_v2_i64(data : &[u8]) -> Result<i64, Error> {
+    return match data.len() {
+        1 => Ok(i64::from(data[0] as i8)),
+        2 => Ok(i64::from(NumberCodec::decode_u16_le(data) as i16)),
+        4 => Ok(i64::from(NumberCodec::decode_u32_le(data) as i32)),
+        8 => Ok(NumberCodec::decode_u64_le(data) as i64),
+        _ => Err(Error::CorruptedDataBytes(
+            "Failed to decode row v2 data as i64".to_owned(),
+            Box::from(data)
+        )),
+    }
+}
+
 fn read_le_bytes<'a, T>(buf: &mut &'a [u8], len: usize) -> Result<LeBytes<'a, T>, Error>
 where
     T: PrimInt,
@@ -641,4 +1172,96 @@ impl<'a, T: PrimInt> LeBytes<'a, T> {
             Err(base + (cmp == Less) as usize)
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tidbtypes::CIStr;
+
+    fn get_column(tp : FieldTypeTp, flen : u32, decimal : i32, elems : &[&str]) -> ColumnInfo {
+        let json = format!(
+            r#"{{"id":1,"name":{{"O":"c","L":"c"}},"offset":0,"type":{{"Tp":{},"Flag":0,"Flen":{},"Decimal":{},"Charset":"utf8mb4","Collate":"utf8mb4_bin","Elems":{}}},"state":5,"comment":"","hidden":false,"version":2}}"#,
+            tp as u8, flen, decimal, serde_json::to_string(elems).unwrap()
+        );
+        return serde_json::from_str(&json).unwrap();
+    }
+
+    fn get_named_column(id : i64, name : &str, tp : FieldTypeTp) -> ColumnInfo {
+        let mut col = get_column(tp, 0, 0, &[]);
+        col.id = id;
+        col.name = CIStr { O : name.to_string(), L : name.to_lowercase() };
+        return col;
+    }
+
+    fn get_table_info(cols : Vec<ColumnInfo>) -> TableInfo {
+        let json = r#"{"id":100,"name":{"O":"t","L":"t"},"charset":"utf8mb4","collate":"utf8mb4_bin","cols":[],"index_info":null,"state":5,"pk_is_handle":false,"is_common_handle":false,"common_handle_version":0,"comment":"","auto_inc_id":0,"auto_id_cache":0,"update_timestamp":0,"version":5}"#;
+        let mut table_info : TableInfo = serde_json::from_str(json).unwrap();
+        table_info.cols = cols;
+        return table_info;
+    }
+
+    #[test]
+    fn test_restore_changed_columns() {
+        //c is changed from int to varchar by MODIFY COLUMN, its id is changed from 2 to 3.
+        let old_table = get_table_info(vec![get_named_column(1, "a", FieldTypeTp::LongLong), get_named_column(2, "c", FieldTypeTp::LongLong)]);
+        let new_table = get_table_info(vec![get_named_column(1, "a", FieldTypeTp::LongLong), get_named_column(3, "c", FieldTypeTp::VarChar)]);
+        let dropped_table = get_table_info(vec![get_named_column(1, "a", FieldTypeTp::LongLong)]);
+        let version = |commit_ts : u64, table_info : &TableInfo| TableInfoVersion { commit_ts, table_info : table_info.clone() };
+
+        let key = tidb_query_datatype::codec::table::encode_row_key(100, 1);
+        let val = encode_row_v2(vec![(1, Some(1i64.to_le_bytes().to_vec())), (2, Some(5i64.to_le_bytes().to_vec()))]);
+        let new_row_data = || RowData::new(Box::from(key.as_slice()), Box::from(val.as_slice()), 1.into(), &new_table).unwrap();
+
+        let mut row_data = new_row_data();
+        row_data.restore_changed_columns(&new_table, &[version(1, &old_table), version(2, &new_table)]).unwrap();
+        assert_eq!(row_data.get_restored_data(3), Some(Some(b"5".as_slice())));
+
+        //DROP COLUMN c and ADD COLUMN c, the new c doesn't take the value of the old one.
+        let mut row_data = new_row_data();
+        row_data.restore_changed_columns(&new_table, &[version(1, &old_table), version(2, &dropped_table), version(3, &new_table)]).unwrap();
+        assert_eq!(row_data.get_restored_data(3), None);
+    }
+
+    fn convert_to_string(d : Datum, col : &ColumnInfo) -> String {
+        let data = convert_datum_for_column(d, col).unwrap().unwrap();
+        return DatumRef::parse_from(&data, col).try_to_string().unwrap();
+    }
+
+    #[test]
+    fn test_convert_decimal() {
+        //DECIMAL(10,2) to DECIMAL(5,2)
+        let col = get_column(FieldTypeTp::NewDecimal, 5, 2, &[]);
+        let dec = |s : &str| Datum::Bytes(s.as_bytes().to_vec()).into_dec().unwrap();
+        assert_eq!(convert_to_string(Datum::Dec(dec("123.45")), &col), "123.45");
+        assert_eq!(convert_to_string(Datum::Dec(dec("12.346")), &col), "12.35");
+        assert_eq!(convert_to_string(Datum::Dec(dec("12345.67")), &col), "999.99");
+        assert_eq!(convert_to_string(Datum::Dec(dec("-12345.67")), &col), "-999.99");
+        //rounded up out of the precision.
+        assert_eq!(convert_to_string(Datum::Dec(dec("999.996")), &col), "999.99");
+    }
+
+    #[test]
+    fn test_enum_and_set() {
+        let col = get_column(FieldTypeTp::Enum, 0, 0, &["a", "b"]);
+        assert_eq!(DatumRef::parse_from(&[2], &col).as_enum_val().unwrap(), "b");
+        assert!(DatumRef::parse_from(&[3], &col).as_enum_val().is_err());
+
+        let col = get_column(FieldTypeTp::Set, 0, 0, &["a", "b", "c"]);
+        assert_eq!(DatumRef::parse_from(&[5], &col).as_set_vals().unwrap(), vec!["a", "c"]);
+        assert!(DatumRef::parse_from(&[1], &get_column(FieldTypeTp::Enum, 0, 0, &["a"])).as_set_vals().is_err());
+    }
+
+    #[test]
+    fn test_duration() {
+        let col = get_column(FieldTypeTp::Duration, 10, 0, &[]);
+        //TIME '00:00:00' in 1 byte.
+        assert_eq!(DatumRef::parse_from(&[0], &col).as_duration().unwrap().to_string(), "00:00:00");
+        //TIME '00:00:01' in 4 bytes.
+        let data = 1_000_000_000i32.to_le_bytes();
+        assert_eq!(DatumRef::parse_from(&data, &col).as_duration().unwrap().to_string(), "00:00:01");
+        let data = (-3_600_000_000_000i64).to_le_bytes();
+        assert_eq!(DatumRef::parse_from(&data, &col).as_duration().unwrap().to_string(), "-01:00:00");
+        assert!(DatumRef::parse_from(&[0, 0, 0], &col).as_duration().is_err());
+    }
+}
@@ -17,6 +17,13 @@ pub enum Error {
     #[error("CorruptDataString: {0}")]
     CorruptedDataString(String, String),
 
+    #[error("MissingBlobFile: {0}")]
+    MissingBlobFile(String, Box<[u8]>),
+
+    //a committed put in cf write whose value can't be found in cf default.
+    #[error("DanglingWrite: {0}")]
+    DanglingWrite(String, Box<[u8]>),
+
     #[error("IO: {0}")]
     IO(String),
 
@@ -30,9 +37,40 @@ impl  From<Error> for String {
     }
 }
 
+//the errors of a single record, the other records can still be read.
+pub fn is_record_error(err : &Error) -> bool {
+    return match err {
+        Error::CorruptedData(_)
+        | Error::CorruptedDataBytes(_, _)
+        | Error::CorruptedDataString(_, _)
+        | Error::MissingBlobFile(_, _)
+        | Error::DanglingWrite(_, _)
+        | Error::Other(_) => true,
+        Error::StorageNodeError(_)
+        | Error::IO(_) => false,
+    };
+}
+
+pub fn get_error_kind(err : &Error) -> &'static str {
+    return match err {
+        Error::StorageNodeError(_) => "StorageNodeError",
+        Error::CorruptedData(_) => "CorruptedData",
+        Error::CorruptedDataBytes(_, _) => "CorruptedDataBytes",
+        Error::CorruptedDataString(_, _) => "CorruptedDataString",
+        Error::MissingBlobFile(_, _) => "MissingBlobFile",
+        Error::DanglingWrite(_, _) => "DanglingWrite",
+        Error::IO(_) => "IO",
+        Error::Other(_) => "Other",
+    };
+}
+
 pub fn display_corrupted_err_data(err : &Error) {
     if let Error::CorruptedDataBytes(_, data) = err {
         display_bytes_err_data(&data);
+    } else if let Error::MissingBlobFile(_, key) = err {
+        display_bytes_err_data(&key);
+    } else if let Error::DanglingWrite(_, key) = err {
+        display_bytes_err_data(&key);
     } else if let Error::CorruptedDataString(_, data) = err {
         display_string_err_data(&data);
     }
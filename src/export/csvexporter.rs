@@ -4,7 +4,7 @@ use crossbeam_channel::Receiver;
 use csv::Writer;
 use tidb_query_datatype::FieldTypeTp;
 
-use crate::{errors::{Error, self}, datum::{DatumRef, RowData}, tidbtypes::TableInfo};
+use crate::{errors::{Error, self}, datum::{DatumRef, RowData}, tidbtypes::TableInfo, mvcc::ErrorRecord, quarantine::Quarantine, rowfilter::RowSelection};
 
 use super::{FileWriteWrap, buf::LinkedBuffer, LinkedBufferWrapper, exporter::{TiDBFileExporter, TiDBExporter}};
 
@@ -14,6 +14,8 @@ pub struct CsvExporter {
     table_info : TableInfo,
     thread_num : usize,
     is_debug_mode : bool,
+    quarantine : Option<Arc<Quarantine>>,
+    selection : Option<Arc<RowSelection>>,
 }
 
 impl CsvExporter {
@@ -25,6 +27,8 @@ impl CsvExporter {
                     fw : Arc::new(Mutex::new(fw)),
                     thread_num : 3,
                     is_debug_mode : false,
+                    quarantine : None,
+                    selection : None,
                 }
             },
             Err(e) => panic!("{}", e.to_string()),
@@ -42,15 +46,29 @@ impl TiDBExporter for CsvExporter {
             let table_info = self.table_info.clone();
             let is_debug_mode = self.is_debug_mode;
             let is_panic_thread = is_panic_ctx.clone();
+            let quarantine = self.quarantine.clone();
+            let selection = self.selection.clone();
             let handle = thread::spawn(move || {
-                let mut export_writer = Box::new(CsvWriter::new(&fw_arc));
+                let mut export_writer = Box::new(CsvWriter::new(&fw_arc, selection));
                 for blocks in rx_thread {
                     if is_panic_thread.load(std::sync::atomic::Ordering::SeqCst) {
                         //somewhere panic
                         return;
                     }
                     for row_data in blocks {
-                        if let Err(e) = export_writer.write_row_data(row_data, &table_info) {
+                        if let Err(e) = export_writer.write_row_data(&row_data, &table_info) {
+                            if let Some(q) = quarantine.as_ref().filter(|_| errors::is_record_error(&e)) {
+                                //the cf of the value is unknown here.
+                                let record = ErrorRecord {
+                                    cf : "",
+                                    key : Box::from(row_data.get_key()),
+                                    value : Box::from(row_data.get_value()),
+                                };
+                                match q.add(Some(&record), &e) {
+                                    Ok(_) => continue,
+                                    Err(qe) => print!("{}", qe.to_string()),
+                                }
+                            }
                             print!("{}", e.to_string());
                             if is_debug_mode {
                                 errors::display_corrupted_err_data(&e);
@@ -77,6 +95,14 @@ impl TiDBExporter for CsvExporter {
     fn set_debug_mode(&mut self, is_debug : bool) {
         self.is_debug_mode = is_debug
     }
+
+    fn set_quarantine(&mut self, quarantine : Arc<Quarantine>) {
+        self.quarantine = Some(quarantine);
+    }
+
+    fn set_row_selection(&mut self, selection : Arc<RowSelection>) {
+        self.selection = Some(selection);
+    }
 }
 
 
@@ -85,10 +111,11 @@ pub struct CsvWriter<'b> {
     writed_row_num : usize,
     buffer : Rc<RefCell<LinkedBuffer>>,
     fw : &'b Mutex<FileWriteWrap>,
+    selection : Option<Arc<RowSelection>>,
 }
 
 impl CsvWriter<'_> {
-    pub fn new<'b>(fw : &'b Mutex<FileWriteWrap>) -> CsvWriter<'b> {
+    pub fn new<'b>(fw : &'b Mutex<FileWriteWrap>, selection : Option<Arc<RowSelection>>) -> CsvWriter<'b> {
         let buf = Rc::new(RefCell::new(LinkedBuffer::new(1024 * 1024 * 10, 5, false)));//100MB
         
         let csv_writer = match Self::get_inner_csv_writer(buf.clone()) {
@@ -99,7 +126,8 @@ impl CsvWriter<'_> {
             csv_writer,
             writed_row_num : 0,
             buffer : buf.clone(),
-            fw
+            fw,
+            selection,
         };
     }
 
@@ -113,7 +141,9 @@ impl CsvWriter<'_> {
             | FieldTypeTp::Short
             | FieldTypeTp::Int24
             | FieldTypeTp::Long
-            | FieldTypeTp::LongLong => true,
+            | FieldTypeTp::LongLong
+            | FieldTypeTp::Bit
+            | FieldTypeTp::Year => true,
             _ => false
         }
     }
@@ -128,8 +158,19 @@ impl CsvWriter<'_> {
         return Ok(csv_writer);
     }
 
-    fn write_row_data(&mut self, row_data : Box<RowData>, table_info : &TableInfo) -> Result<(), Error> {
-        let datum_refs = row_data.get_datum_refs(table_info)?;
+    fn write_row_data(&mut self, row_data : &RowData, table_info : &TableInfo) -> Result<(), Error> {
+        let datum_refs = match self.selection.as_ref() {
+            Some(selection) => {
+                let mut datum_refs = row_data.get_datum_refs_of_cols(selection.get_col_offsets().iter().map(|o| &table_info.cols[*o]))?;
+                if !selection.is_selected(&datum_refs)? {
+                    return Ok(());
+                }
+                //the columns only in the condition are not exported.
+                datum_refs.truncate(selection.get_export_col_num());
+                datum_refs
+            },
+            None => row_data.get_datum_refs(table_info)?,
+        };
 
         let mut data_record = csv::StringRecord::with_capacity(1024, datum_refs.len());
 
@@ -176,7 +217,7 @@ impl CsvWriter<'_> {
 
         let res = self.csv_writer.write_record(&data_record);
         if res.is_err() {
-            return Err(Error::Other(res.err().unwrap().to_string()));
+            return Err(Error::IO(res.err().unwrap().to_string()));
         }
         self.writed_row_num += 1;
 
@@ -196,7 +237,7 @@ impl CsvWriter<'_> {
                     }
                     (*(self.buffer)).borrow_mut().reset();
                 } else {
-                    return Err(Error::Other("lock for writing failed.".to_string()));
+                    return Err(Error::IO("lock for writing failed.".to_string()));
                 }
             }
         }
@@ -229,4 +270,39 @@ impl CsvWriter<'_> {
             return Err(Error::Other("lock for writing failed.".to_string()));
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::datum;
+
+    #[test]
+    fn test_write_origin_defaults() {
+        //b BIT(8) DEFAULT b'101' and y YEAR DEFAULT 2023 are added after the row is written.
+        let col = |id : i64, name : &str, tp : FieldTypeTp, flen : u32, origin_default : &str| format!(
+            r#"{{"id":{},"name":{{"O":"{}","L":"{}"}},"offset":{},"type":{{"Tp":{},"Flag":0,"Flen":{},"Decimal":0,"Charset":"binary","Collate":"binary","Elems":null}},"state":5,"comment":"","hidden":false,"version":2,{}}}"#,
+            id, name, name, id - 1, tp as u8, flen, origin_default
+        );
+        let json = format!(
+            r#"{{"id":100,"name":{{"O":"t","L":"t"}},"charset":"utf8mb4","collate":"utf8mb4_bin","cols":[{},{},{}],"index_info":null,"state":5,"pk_is_handle":false,"is_common_handle":false,"common_handle_version":0,"comment":"","auto_inc_id":0,"auto_id_cache":0,"update_timestamp":0,"version":5}}"#,
+            col(1, "id", FieldTypeTp::LongLong, 20, r#""origin_default":null"#),
+            col(2, "b", FieldTypeTp::Bit, 8, r#""origin_default":"\u0005","origin_default_bit":"BQ==""#),
+            col(3, "y", FieldTypeTp::Year, 4, r#""origin_default":"2023""#)
+        );
+        let table_info : TableInfo = serde_json::from_str(&json).unwrap();
+
+        let key = tidb_query_datatype::codec::table::encode_row_key(100, 1);
+        let val = datum::encode_row_v2(vec![(1, Some(1i64.to_le_bytes().to_vec()))]);
+        let mut row_data = RowData::new(Box::from(key.as_slice()), Box::from(val.as_slice()), 1.into(), &table_info).unwrap();
+        row_data.restore_origin_defaults(&datum::get_origin_defaults(&table_info)).unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("t.csv");
+        let fw = Mutex::new(FileWriteWrap::new(path.to_str().unwrap(), 0, false).unwrap());
+        let mut csv_writer = CsvWriter::new(&fw, None);
+        csv_writer.write_row_data(&row_data, &table_info).unwrap();
+        csv_writer.flush().unwrap();
+
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "1,5,2023\n");
+    }
+}
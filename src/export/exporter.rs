@@ -2,7 +2,7 @@ use std::{thread::JoinHandle, sync::{Arc, atomic::AtomicBool}};
 
 use crossbeam_channel::Receiver;
 
-use crate::{errors::Error, datum::RowData};
+use crate::{errors::Error, datum::RowData, quarantine::Quarantine, rowfilter::RowSelection};
 
 use super::FileWriteWrap;
 
@@ -14,6 +14,12 @@ pub trait TiDBExporter {
     fn set_thread_num(&mut self, num : usize);
 
     fn set_debug_mode(&mut self, is_debug : bool);
+
+    //the rows which can't be written are logged into the quarantine instead of aborting the export.
+    fn set_quarantine(&mut self, quarantine : Arc<Quarantine>);
+
+    //only the selected columns of the matched rows are exported.
+    fn set_row_selection(&mut self, selection : Arc<RowSelection>);
 }
 
 pub trait TiDBFileExporter {
@@ -18,7 +18,7 @@ mod csvexporter;
 
 
 pub trait TiDBExportWriter {
-    fn write_row_data(&mut self, row_data : Box<RowData>, table_info : &TableInfo) -> Result<(), Error>;
+    fn write_row_data(&mut self, row_data : &RowData, table_info : &TableInfo) -> Result<(), Error>;
     fn flush(&mut self) -> Result<(), Error>;
     fn writed_row_num(&self) -> usize;
 }
@@ -0,0 +1,197 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::{errors::Error, storagenode::RocksDbStorageNode, tidbtypes::{self, TableInfo, IndexInfo}};
+use crate::datum::DatumRef;
+use crate::indexdataiterator;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IndexProblemKind {
+    //a row without the entry in the index.
+    MissingEntry,
+    //an entry in the index without the row.
+    DanglingEntry,
+    //the values of the entry are not the same as the ones of the row.
+    Mismatch,
+}
+
+impl fmt::Display for IndexProblemKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IndexProblemKind::MissingEntry => write!(f, "missing index entry"),
+            IndexProblemKind::DanglingEntry => write!(f, "dangling index entry"),
+            IndexProblemKind::Mismatch => write!(f, "value mismatch"),
+        }
+    }
+}
+
+pub struct IndexProblem<'a> {
+    pub index_info : &'a IndexInfo,
+    pub kind : IndexProblemKind,
+    pub handle : Box<[u8]>,
+    //the values of the index entry, empty for the missing ones.
+    pub index_values : Vec<String>,
+    //the values of the index columns of the row, empty for the dangling ones.
+    pub row_values : Vec<String>,
+}
+
+pub struct IndexCheckSummary<'a> {
+    pub index_info : &'a IndexInfo,
+    pub entry_num : usize,
+    pub missing_num : usize,
+    pub dangling_num : usize,
+    pub mismatch_num : usize,
+}
+
+//the indexes which have their own entries, the clustered primary key is the handle of the rows.
+//the expression indexes are on the virtual columns which are not stored in the rows.
+pub fn get_checkable_indexes(table_info : &TableInfo) -> Vec<&IndexInfo> {
+    return table_info.index_info.iter().filter(|index_info| {
+        if index_info.state != tidbtypes::StatePublic || index_info.is_global {
+            return false;
+        }
+        if index_info.is_primary && (table_info.pk_is_handle || table_info.is_common_handle) {
+            return false;
+        }
+        return index_info.idx_cols.iter().all(|idx_col| {
+            table_info.cols.get(idx_col.offset as usize).map_or(false, |col| !col.hidden)
+        });
+    }).collect();
+}
+
+//checks the indexes of the table with the rows like ADMIN CHECK TABLE. the entries of the indexes
+//are held in memory by handle, and matched with the rows scanned in the order of handle.
+pub fn check_table<'a>(rocksdb_node : &RocksDbStorageNode, table_info : &'a TableInfo, on_problem : &mut dyn FnMut(&IndexProblem)) -> Result<Vec<IndexCheckSummary<'a>>, Error> {
+    let indexes = get_checkable_indexes(table_info);
+    let mut summaries = Vec::with_capacity(indexes.len());
+    //handle -> values of the entries.
+    let mut index_entries : Vec<HashMap<Box<[u8]>, Vec<Vec<String>>>> = Vec::with_capacity(indexes.len());
+
+    for &index_info in indexes.iter() {
+        let mut entries : HashMap<Box<[u8]>, Vec<Vec<String>>> = HashMap::new();
+        let mut entry_num = 0;
+        for index_data_res in rocksdb_node.get_index_data_iter(table_info, index_info)? {
+            let index_data = index_data_res?;
+            let mut values = Vec::with_capacity(index_info.idx_cols.len());
+            for (idx_col, d) in index_info.idx_cols.iter().zip(index_data.get_values()) {
+                let col = &table_info.cols[idx_col.offset as usize];
+                values.push(indexdataiterator::format_index_value(d, col)?);
+            }
+            entries.entry(Box::from(index_data.get_handle())).or_default().push(values);
+            entry_num += 1;
+        }
+
+        index_entries.push(entries);
+        summaries.push(IndexCheckSummary {
+            index_info,
+            entry_num,
+            missing_num : 0,
+            dangling_num : 0,
+            mismatch_num : 0,
+        });
+    }
+
+    for row_data_res in rocksdb_node.get_table_data_iter(table_info)? {
+        let row_data = row_data_res?;
+        let datum_refs = row_data.get_datum_refs(table_info)?;
+
+        for (i, &index_info) in indexes.iter().enumerate() {
+            let row_values = get_row_index_values(&datum_refs, table_info, index_info)?;
+            let mut entry_values_list = match index_entries[i].remove(row_data.get_handle()) {
+                Some(l) => l,
+                None => {
+                    summaries[i].missing_num += 1;
+                    on_problem(&IndexProblem {
+                        index_info,
+                        kind : IndexProblemKind::MissingEntry,
+                        handle : Box::from(row_data.get_handle()),
+                        index_values : vec![],
+                        row_values,
+                    });
+                    continue;
+                },
+            };
+
+            match entry_values_list.iter().position(|values| values.eq(&row_values)) {
+                Some(pos) => {
+                    entry_values_list.remove(pos);
+                },
+                None => {
+                    summaries[i].mismatch_num += 1;
+                    on_problem(&IndexProblem {
+                        index_info,
+                        kind : IndexProblemKind::Mismatch,
+                        handle : Box::from(row_data.get_handle()),
+                        index_values : entry_values_list.remove(0),
+                        row_values,
+                    });
+                },
+            }
+
+            //the other entries of the same handle are left by the old values of the row.
+            for index_values in entry_values_list {
+                summaries[i].dangling_num += 1;
+                on_problem(&IndexProblem {
+                    index_info,
+                    kind : IndexProblemKind::DanglingEntry,
+                    handle : Box::from(row_data.get_handle()),
+                    index_values,
+                    row_values : vec![],
+                });
+            }
+        }
+    }
+
+    for (i, &index_info) in indexes.iter().enumerate() {
+        for (handle, entry_values_list) in index_entries[i].drain() {
+            for index_values in entry_values_list {
+                summaries[i].dangling_num += 1;
+                on_problem(&IndexProblem {
+                    index_info,
+                    kind : IndexProblemKind::DanglingEntry,
+                    handle : handle.clone(),
+                    index_values,
+                    row_values : vec![],
+                });
+            }
+        }
+    }
+
+    return Ok(summaries);
+}
+
+//the values of the index columns of the row, the prefix index columns are truncated as tidb does.
+fn get_row_index_values(datum_refs : &[DatumRef], table_info : &TableInfo, index_info : &IndexInfo) -> Result<Vec<String>, Error> {
+    let mut ret = Vec::with_capacity(index_info.idx_cols.len());
+    for idx_col in index_info.idx_cols.iter() {
+        let d = match datum_refs.get(idx_col.offset as usize) {
+            Some(d) => d,
+            None => return Err(Error::CorruptedData(format!("the column of index {} is not found, offset: {}", index_info.idx_name.O, idx_col.offset))),
+        };
+        let mut value = d.try_to_string()?;
+
+        //the length of the prefix is measured in characters, or in bytes for the binary strings.
+        if idx_col.length > 0 && d.is_string() {
+            let length = idx_col.length as usize;
+            if table_info.cols[idx_col.offset as usize].field_type.Charset == "binary" {
+                let mut bytes = value.into_bytes();
+                bytes.truncate(length);
+                value = String::from_utf8_lossy(&bytes).to_string();
+            } else if let Some((pos, _)) = value.char_indices().nth(length) {
+                value.truncate(pos);
+            }
+        }
+        ret.push(value);
+    }
+    return Ok(ret);
+}
+
+//the int handle is decoded from the memcomparable format, the common handle is in hex.
+pub fn format_handle(table_info : &TableInfo, handle : &[u8]) -> String {
+    if !table_info.is_common_handle && handle.len() == 8 {
+        let mut buf = [0u8; 8];
+        buf.copy_from_slice(handle);
+        return ((u64::from_be_bytes(buf) ^ (1 << 63)) as i64).to_string();
+    }
+    return hex::encode_upper(handle);
+}
@@ -0,0 +1,283 @@
+use tidb_query_datatype::codec::datum::{self, Datum};
+use tidb_query_datatype::codec::row::v2::RowSlice;
+use tidb_query_datatype::FieldTypeTp;
+use txn_types::TimeStamp;
+
+use crate::{tidbtypes::{TableInfo, IndexInfo, ColumnInfo}, errors::Error, mvcc::MvccIterator};
+use crate::datum::{self as rowdatum, DatumRef};
+
+//t{table_id}_i{index_id}
+const INDEX_KEY_PREFIX_LEN : usize = tidb_query_datatype::codec::table::PREFIX_LEN + tidb_query_datatype::codec::table::ID_LEN;
+
+//the values of an index with at most 9 bytes are encoded by the old versions of tidb.
+const MAX_OLD_ENCODE_VALUE_LEN : usize = 9;
+//from tidb definition
+const COMMON_HANDLE_FLAG : u8 = 127;
+const PARTITION_ID_FLAG : u8 = 126;
+const INDEX_VERSION_FLAG : u8 = 125;
+//the restored data is encoded as row format v2, and begins with its codec version.
+const RESTORE_DATA_FLAG : u8 = 128;
+
+//the collations whose keys are trimmed the trailing spaces, only the number of them is restored.
+const PADDING_BIN_COLLATIONS : [&str; 5] = ["utf8mb4_bin", "utf8_bin", "latin1_bin", "ascii_bin", "gbk_bin"];
+
+//an index entry, the values are converted to the same types as the ones in the rows.
+pub struct IndexData {
+    pub append_ts : TimeStamp,
+    //the encoded handle, the same as the one in the record key.
+    handle : Box<[u8]>,
+    //the values of the index columns in the order of idx_cols.
+    values : Vec<Datum>,
+}
+
+impl IndexData {
+    pub fn new(key_data : &[u8], val_data : &[u8], append_ts : TimeStamp, table_info : &TableInfo, index_info : &IndexInfo) -> Result<IndexData, Error> {
+        let mut key_remain = match key_data.get(INDEX_KEY_PREFIX_LEN..) {
+            Some(k) => k,
+            None => return Err(Error::CorruptedDataBytes("the index key is too short.".to_string(), Box::from(key_data))),
+        };
+
+        //the index columns are followed by the handle in the key for non-unique indexes and
+        //the unique ones with null values.
+        let mut values_len = 0;
+        for _ in index_info.idx_cols.iter() {
+            let (datum_data, remain) = match datum::split_datum(key_remain, false) {
+                Ok(r) => r,
+                Err(_) => return Err(Error::CorruptedDataBytes("split the index key datum error.".to_string(), Box::from(key_data))),
+            };
+            values_len += datum_data.len();
+            key_remain = remain;
+        }
+
+        let mut values_data = &key_data[INDEX_KEY_PREFIX_LEN..INDEX_KEY_PREFIX_LEN + values_len];
+        let mut values = match datum::decode(&mut values_data) {
+            Ok(d) => d,
+            Err(_) => return Err(Error::CorruptedDataBytes("decode the index key datums error.".to_string(), Box::from(key_data))),
+        };
+
+        let value_segs = split_index_value(val_data)?;
+        let handle : Box<[u8]> = if !key_remain.is_empty() {
+            if table_info.is_common_handle {
+                Box::from(key_remain)
+            } else {
+                //the int handle is encoded as a datum in the key, without the flag in the record key.
+                match key_remain.get(1..) {
+                    Some(h) if h.len() == 8 => Box::from(h),
+                    _ => return Err(Error::CorruptedDataBytes("invalid int handle in the index key.".to_string(), Box::from(key_data))),
+                }
+            }
+        } else if let Some(h) = value_segs.common_handle {
+            Box::from(h)
+        } else if let Some(h) = value_segs.int_handle {
+            get_record_int_handle(h)
+        } else {
+            return Err(Error::CorruptedDataBytes("no handle in the index entry.".to_string(), Box::from(val_data)));
+        };
+
+        let mut index_data = IndexData {
+            append_ts,
+            handle,
+            values : Vec::with_capacity(values.len()),
+        };
+
+        let restored_row = match value_segs.restored_data {
+            Some(data) => match RowSlice::from_bytes(data) {
+                Ok(r) => Some(r),
+                Err(_) => return Err(Error::CorruptedDataBytes("parse the restored data of the index error.".to_string(), Box::from(val_data))),
+            },
+            None => None,
+        };
+
+        for (idx_col, d) in index_info.idx_cols.iter().zip(values.drain(..)) {
+            let col = match table_info.cols.get(idx_col.offset as usize) {
+                Some(c) => c,
+                None => return Err(Error::CorruptedData(format!("the column of index {} is not found, offset: {}", index_info.idx_name.O, idx_col.offset))),
+            };
+            let restored_value = match restored_row.as_ref() {
+                Some(row) => match row.search_in_non_null_ids(col.id) {
+                    Ok(Some((start, offset))) => Some(&row.values()[start..offset]),
+                    Ok(None) => None,
+                    Err(_) => return Err(Error::CorruptedDataBytes(format!("search column {} in the restored data error.", col.id), Box::from(val_data))),
+                },
+                None => None,
+            };
+            index_data.values.push(restore_index_value(d, restored_value, col)?);
+        }
+
+        return Ok(index_data);
+    }
+
+    pub fn get_handle(&self) -> &[u8] {
+        return &self.handle;
+    }
+
+    pub fn get_values(&self) -> &[Datum] {
+        return &self.values;
+    }
+}
+
+//the key datum is a sort key for the strings of new collations, the original value is in the
+//restored data. for the padding bin collations, only the number of the trailing spaces is restored.
+fn restore_index_value(d : Datum, restored_value : Option<&[u8]>, col : &ColumnInfo) -> Result<Datum, Error> {
+    //the other values in keys are in the same formats as the ones in rows, such as the packed u64 of times.
+    let restored_value = match restored_value {
+        Some(v) => v,
+        None => return Ok(d),
+    };
+
+    if PADDING_BIN_COLLATIONS.contains(&col.field_type.Collate.as_str()) {
+        if let Datum::Bytes(mut b) = d {
+            let trailing_spaces = match DatumRef::parse_from(restored_value, &padding_count_col(col)).to_datum()? {
+                Datum::U64(n) => n as usize,
+                Datum::I64(n) => n as usize,
+                _ => 0,
+            };
+            b.extend(std::iter::repeat(b' ').take(trailing_spaces));
+            return Ok(Datum::Bytes(b));
+        }
+    }
+
+    return DatumRef::parse_from(restored_value, col).to_datum();
+}
+
+//the number of the trailing spaces is restored as an unsigned integer.
+fn padding_count_col(col : &ColumnInfo) -> ColumnInfo {
+    let mut count_col = col.clone();
+    count_col.field_type.Tp = FieldTypeTp::LongLong as u8;
+    count_col.field_type.Flag |= tidb_query_datatype::FieldTypeFlag::UNSIGNED.bits();
+    return count_col;
+}
+
+//the int handle in the index value is the big-endian u64 of it, while the one in the record key
+//is comparable with the sign bit flipped.
+fn get_record_int_handle(value_handle : &[u8]) -> Box<[u8]> {
+    let mut handle : Box<[u8]> = Box::from(value_handle);
+    handle[0] ^= 0x80;
+    return handle;
+}
+
+struct IndexValueSegments<'a> {
+    int_handle : Option<&'a [u8]>,
+    common_handle : Option<&'a [u8]>,
+    restored_data : Option<&'a [u8]>,
+}
+
+//TailLen | [VersionFlag Version] | [CommonHandle] | [PartitionID] | [RestoredData] | Padding | [IntHandle] | [UntouchedFlag]
+//or [IntHandle] | [UntouchedFlag] of the old encoding.
+fn split_index_value(val_data : &[u8]) -> Result<IndexValueSegments, Error> {
+    let mut segs = IndexValueSegments {
+        int_handle : None,
+        common_handle : None,
+        restored_data : None,
+    };
+
+    if val_data.len() <= MAX_OLD_ENCODE_VALUE_LEN {
+        if val_data.len() >= 8 {
+            segs.int_handle = Some(&val_data[..8]);
+        }
+        return Ok(segs);
+    }
+
+    let tail_len = val_data[0] as usize;
+    if tail_len + 1 > val_data.len() {
+        return Err(Error::CorruptedDataBytes("invalid tail length of the index value.".to_string(), Box::from(val_data)));
+    }
+    let tail = &val_data[val_data.len() - tail_len..];
+    if tail.len() >= 8 {
+        segs.int_handle = Some(&tail[..8]);
+    }
+
+    let mut options = &val_data[1..val_data.len() - tail_len];
+    if options.len() >= 2 && options[0] == INDEX_VERSION_FLAG {
+        options = &options[2..];
+    }
+    if options.first() == Some(&COMMON_HANDLE_FLAG) {
+        if options.len() < 3 {
+            return Err(Error::CorruptedDataBytes("invalid common handle of the index value.".to_string(), Box::from(val_data)));
+        }
+        let handle_end = 3 + ((options[1] as usize) << 8 | options[2] as usize);
+        segs.common_handle = match options.get(3..handle_end) {
+            Some(h) => Some(h),
+            None => return Err(Error::CorruptedDataBytes("invalid common handle of the index value.".to_string(), Box::from(val_data))),
+        };
+        options = &options[handle_end..];
+    }
+    if options.first() == Some(&PARTITION_ID_FLAG) {
+        options = options.get(9..).unwrap_or_default();
+    }
+    if options.first() == Some(&RESTORE_DATA_FLAG) {
+        segs.restored_data = Some(options);
+    }
+
+    return Ok(segs);
+}
+
+//the entries of an index, the latest visible version of each key.
+pub struct IndexDataIterator<'a, 'b> {
+    table_info : &'a TableInfo,
+    index_info : &'a IndexInfo,
+    mvcc_iter : MvccIterator<'b>,
+}
+
+impl <'a, 'b> IndexDataIterator<'a, 'b> {
+    pub fn new(table_info : &'a TableInfo, index_info : &'a IndexInfo, mvcc_iter : MvccIterator<'b>) -> IndexDataIterator<'a, 'b> {
+        return IndexDataIterator {
+            table_info,
+            index_info,
+            mvcc_iter,
+        };
+    }
+
+    fn get_inner_index_data(&mut self) -> Option<Result<IndexData, Error>> {
+        let mvcc_value = match self.mvcc_iter.next()? {
+            Ok(v) => v,
+            Err(e) => return Some(Err(e)),
+        };
+
+        return Some(IndexData::new(&mvcc_value.key, &mvcc_value.value, mvcc_value.commit_ts, self.table_info, self.index_info));
+    }
+}
+
+impl <'a, 'b> Iterator for IndexDataIterator<'a, 'b> {
+    type Item = Result<IndexData, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        return self.get_inner_index_data();
+    }
+}
+
+//the index value as the same string as the one of the row exported, "NULL" for null.
+pub fn format_index_value(d : &Datum, col : &ColumnInfo) -> Result<String, Error> {
+    return match rowdatum::encode_datum_to_v2_bytes(d)? {
+        None => Ok("NULL".to_string()),
+        Some(data) => DatumRef::parse_from(&data, col).try_to_string(),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unique_index_int_handle() {
+        for handle in [0i64, 1, -1, 123456789, -123456789, i64::MAX, i64::MIN] {
+            let row_key = tidb_query_datatype::codec::table::encode_row_key(100, handle);
+            let record_handle = &row_key[row_key.len() - 8..];
+
+            //the old encoding, the handle followed by the untouched flag.
+            let mut val_data = (handle as u64).to_be_bytes().to_vec();
+            let segs = split_index_value(&val_data).unwrap();
+            assert_eq!(get_record_int_handle(segs.int_handle.unwrap()).as_ref(), record_handle);
+            val_data.push(b'1');
+            let segs = split_index_value(&val_data).unwrap();
+            assert_eq!(get_record_int_handle(segs.int_handle.unwrap()).as_ref(), record_handle);
+
+            //the new encoding with the version flag, the handle is in the tail.
+            let mut val_data = vec![8, INDEX_VERSION_FLAG, 1];
+            val_data.extend_from_slice(&(handle as u64).to_be_bytes());
+            let segs = split_index_value(&val_data).unwrap();
+            assert!(segs.common_handle.is_none());
+            assert_eq!(get_record_int_handle(segs.int_handle.unwrap()).as_ref(), record_handle);
+        }
+    }
+}
@@ -0,0 +1,146 @@
+use std::collections::{BTreeMap, HashMap};
+
+use tidb_query_datatype::FieldTypeTp;
+use txn_types::TimeStamp;
+
+use crate::{errors::Error, storagenode::RocksDbStorageNode, tidbtypes::{TableInfo, IndexInfo}};
+use crate::datum::{self, RowData};
+
+//a row rebuilt from the index entries of the same handle.
+pub struct RebuiltRow {
+    //column id -> (commit ts of the entry, value encoded as row format v2, None for null).
+    values : HashMap<i64, (TimeStamp, Option<Box<[u8]>>)>,
+    //indexes of the indexes in which the handle is found.
+    pub found_in : Vec<usize>,
+}
+
+//rebuilds the rows of the table from the entries of the indexes when the records are lost,
+//the entries are joined by handle. the columns covered by none of the indexes are null.
+pub struct IndexRowRebuilder<'a> {
+    table_info : &'a TableInfo,
+    indexes : Vec<&'a IndexInfo>,
+    //handle -> row, in the order of handle.
+    rows : BTreeMap<Box<[u8]>, RebuiltRow>,
+}
+
+impl <'a> IndexRowRebuilder<'a> {
+    pub fn new(table_info : &'a TableInfo, indexes : Vec<&'a IndexInfo>) -> IndexRowRebuilder<'a> {
+        return IndexRowRebuilder {
+            table_info,
+            indexes,
+            rows : BTreeMap::new(),
+        };
+    }
+
+    pub fn get_indexes(&self) -> &[&'a IndexInfo] {
+        return &self.indexes;
+    }
+
+    //the ids of the columns which can be rebuilt, from the indexes or the handle.
+    pub fn get_recoverable_col_ids(&self) -> Vec<i64> {
+        let handle_col_ids = get_handle_col_ids(self.table_info);
+        let mut ret = vec![];
+        for col in self.table_info.cols.iter() {
+            let in_handle = handle_col_ids.contains(&col.id);
+            let in_indexes = self.indexes.iter().any(|index_info| {
+                index_info.idx_cols.iter().any(|idx_col| idx_col.offset == col.offset && !is_prefix_col(self.table_info, idx_col.offset, idx_col.length))
+            });
+            if in_handle || in_indexes {
+                ret.push(col.id);
+            }
+        }
+        return ret;
+    }
+
+    //reads all the entries of the indexes, they are held in memory.
+    pub fn load(&mut self, rocksdb_node : &RocksDbStorageNode) -> Result<(), Error> {
+        for (i, index_info) in self.indexes.iter().enumerate() {
+            for index_data_res in rocksdb_node.get_index_data_iter(self.table_info, index_info)? {
+                let index_data = index_data_res?;
+                let row = self.rows.entry(Box::from(index_data.get_handle())).or_insert_with(|| RebuiltRow {
+                    values : HashMap::new(),
+                    found_in : vec![],
+                });
+                if !row.found_in.contains(&i) {
+                    row.found_in.push(i);
+                }
+
+                for (idx_col, d) in index_info.idx_cols.iter().zip(index_data.get_values()) {
+                    //only the prefix of the value is in the index.
+                    if is_prefix_col(self.table_info, idx_col.offset, idx_col.length) {
+                        continue;
+                    }
+                    let col_id = self.table_info.cols[idx_col.offset as usize].id;
+                    let col_data = datum::encode_datum_to_v2_bytes(d)?.map(|data| data.into_boxed_slice());
+
+                    //the entries left by the old values of the row are older.
+                    match row.values.get(&col_id) {
+                        Some((commit_ts, _)) if *commit_ts >= index_data.append_ts => (),
+                        _ => {
+                            row.values.insert(col_id, (index_data.append_ts, col_data));
+                        },
+                    }
+                }
+            }
+        }
+
+        return Ok(());
+    }
+
+    pub fn into_rows(self) -> impl Iterator<Item = (Box<[u8]>, RebuiltRow)> + 'a {
+        return self.rows.into_iter();
+    }
+}
+
+//the row data of the rebuilt row, the columns not recovered are null.
+pub fn get_rebuilt_row_data(table_info : &TableInfo, handle : &[u8], row : &RebuiltRow) -> Result<RowData, Error> {
+    let handle_col_ids = get_handle_col_ids(table_info);
+    let mut cols : Vec<(i64, Option<Vec<u8>>)> = Vec::with_capacity(table_info.cols.len());
+    let mut commit_ts = TimeStamp::zero();
+    for col in table_info.cols.iter() {
+        //restored from the handle by RowData.
+        if handle_col_ids.contains(&col.id) {
+            continue;
+        }
+        match row.values.get(&col.id) {
+            Some((ts, col_data)) => {
+                if *ts > commit_ts {
+                    commit_ts = *ts;
+                }
+                cols.push((col.id, col_data.as_ref().map(|data| data.to_vec())));
+            },
+            None => cols.push((col.id, None)),
+        }
+    }
+
+    //t{table_id}_r{handle}
+    let mut key_data = tidb_query_datatype::codec::table::encode_row_key(table_info.id, 0);
+    key_data.truncate(tidb_query_datatype::codec::table::PREFIX_LEN);
+    key_data.extend_from_slice(handle);
+
+    return RowData::new(key_data.into_boxed_slice(), datum::encode_row_v2(cols).into_boxed_slice(), commit_ts, table_info);
+}
+
+fn get_handle_col_ids(table_info : &TableInfo) -> Vec<i64> {
+    if table_info.is_common_handle {
+        return table_info.get_common_handle_cols().iter().map(|c| c.id).collect();
+    }
+    if table_info.pk_is_handle {
+        return table_info.cols.iter().filter(|c| c.field_type.has_prikey_flag()).map(|c| c.id).collect();
+    }
+    return vec![];
+}
+
+fn is_prefix_col(table_info : &TableInfo, offset : i32, length : i32) -> bool {
+    if length <= 0 {
+        return false;
+    }
+    return match table_info.cols.get(offset as usize) {
+        Some(col) => {
+            let tp = FieldTypeTp::from_u8(col.field_type.Tp).unwrap_or(FieldTypeTp::Unspecified);
+            //the full length prefix is the same as the value.
+            !(tp == FieldTypeTp::VarChar || tp == FieldTypeTp::String) || (col.field_type.Flen as i32) > length
+        },
+        None => true,
+    };
+}
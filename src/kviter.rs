@@ -0,0 +1,219 @@
+use crate::errors::Error;
+
+//(key, value) of rocksdb, the key is prefixed with 'z' for the data keys.
+pub type KvPair = (Box<[u8]>, Box<[u8]>);
+
+pub type KvIterator<'a> = Box<dyn Iterator<Item = Result<KvPair, Error>> + 'a>;
+
+//merges the iterators which are in ascending order of the key, if the same key is returned
+//by several iterators, only the one from the first iterator is kept.
+pub struct MergedKvIterator<'a> {
+    iters : Vec<KvIterator<'a>>,
+    heads : Vec<Option<KvPair>>,
+    //the error of an iterator is returned by the next call, then the iterator goes on.
+    pending_errors : Vec<Option<Error>>,
+    is_inited : bool,
+    //indexes of the iterators which returned the last key.
+    last_sources : Vec<usize>,
+}
+
+impl <'a> MergedKvIterator<'a> {
+    pub fn new(iters : Vec<KvIterator<'a>>) -> MergedKvIterator<'a> {
+        let heads = iters.iter().map(|_| None).collect();
+        let pending_errors = iters.iter().map(|_| None).collect();
+        return MergedKvIterator {
+            iters,
+            heads,
+            pending_errors,
+            is_inited : false,
+            last_sources : vec![],
+        };
+    }
+
+    pub fn get_last_sources(&self) -> &[usize] {
+        return &self.last_sources;
+    }
+
+    fn fill_head(&mut self, idx : usize) {
+        self.heads[idx] = match self.iters[idx].next() {
+            None => None,
+            Some(Ok(kv)) => Some(kv),
+            Some(Err(e)) => {
+                self.pending_errors[idx] = Some(e);
+                None
+            },
+        };
+    }
+}
+
+impl <'a> Iterator for MergedKvIterator<'a> {
+    type Item = Result<KvPair, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if !self.is_inited {
+            self.is_inited = true;
+            for idx in 0..self.iters.len() {
+                self.fill_head(idx);
+            }
+        }
+
+        //the keys taken are returned before the errors, and the failed iterator is filled again,
+        //so a bad record doesn't end the rest of its iterator.
+        if let Some(idx) = self.pending_errors.iter().position(|e| e.is_some()) {
+            let e = self.pending_errors[idx].take().unwrap();
+            self.fill_head(idx);
+            return Some(Err(e));
+        }
+
+        let mut min_idx : Option<usize> = None;
+        for (idx, head) in self.heads.iter().enumerate() {
+            if let Some((key, _)) = head {
+                match min_idx {
+                    Some(m) if self.heads[m].as_ref().unwrap().0 <= *key => (),
+                    _ => min_idx = Some(idx),
+                }
+            }
+        }
+
+        let min_idx = min_idx?;
+        let kv = self.heads[min_idx].take().unwrap();
+        self.last_sources.clear();
+        for idx in 0..self.heads.len() {
+            let is_same_key = idx == min_idx || self.heads[idx].as_ref().map_or(false, |(key, _)| *key == kv.0);
+            if is_same_key {
+                self.last_sources.push(idx);
+                self.fill_head(idx);
+            }
+        }
+
+        return Some(Ok(kv));
+    }
+}
+
+//(key, value) changed on the keys of another iterator, None for the deleted key.
+pub type KvChange = (Box<[u8]>, Option<Box<[u8]>>);
+
+//applies the changes in ascending order of the key over the base iterator, the changes replace
+//the same keys of the base iterator, such as the writes replayed from the raft log.
+pub struct OverlayKvIterator<'a> {
+    base : KvIterator<'a>,
+    base_head : Option<KvPair>,
+    //the error of the base iterator is returned by the next call, then the base iterator goes on.
+    pending_error : Option<Error>,
+    changes : std::vec::IntoIter<KvChange>,
+    change_head : Option<KvChange>,
+    is_inited : bool,
+}
+
+impl <'a> OverlayKvIterator<'a> {
+    pub fn new(base : KvIterator<'a>, changes : Vec<KvChange>) -> OverlayKvIterator<'a> {
+        return OverlayKvIterator {
+            base,
+            base_head : None,
+            pending_error : None,
+            changes : changes.into_iter(),
+            change_head : None,
+            is_inited : false,
+        };
+    }
+
+    fn fill_base_head(&mut self) {
+        self.base_head = match self.base.next() {
+            None => None,
+            Some(Ok(kv)) => Some(kv),
+            Some(Err(e)) => {
+                self.pending_error = Some(e);
+                None
+            },
+        };
+    }
+}
+
+impl <'a> Iterator for OverlayKvIterator<'a> {
+    type Item = Result<KvPair, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if !self.is_inited {
+            self.is_inited = true;
+            self.change_head = self.changes.next();
+            self.fill_base_head();
+        }
+
+        loop {
+            if let Some(e) = self.pending_error.take() {
+                self.fill_base_head();
+                return Some(Err(e));
+            }
+
+            let is_change_first = match (self.base_head.as_ref(), self.change_head.as_ref()) {
+                (None, None) => return None,
+                (Some(_), None) => false,
+                (None, Some(_)) => true,
+                (Some((base_key, _)), Some((change_key, _))) => change_key <= base_key,
+            };
+
+            if !is_change_first {
+                let kv = self.base_head.take().unwrap();
+                self.fill_base_head();
+                return Some(Ok(kv));
+            }
+
+            let (key, value) = self.change_head.take().unwrap();
+            self.change_head = self.changes.next();
+            //the same key in the base iterator is replaced.
+            if self.base_head.as_ref().map_or(false, |(base_key, _)| *base_key == key) {
+                self.fill_base_head();
+            }
+            if let Some(value) = value {
+                return Some(Ok((key, value)));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kv(key : &str) -> Result<KvPair, Error> {
+        return Ok((Box::from(key.as_bytes()), Box::from(key.as_bytes())));
+    }
+
+    fn err() -> Result<KvPair, Error> {
+        return Err(Error::Other("bad record".to_string()));
+    }
+
+    //the keys, and "!" for the errors.
+    fn collect_keys(iter : impl Iterator<Item = Result<KvPair, Error>>) -> Vec<String> {
+        return iter.map(|res| match res {
+            Ok((key, _)) => String::from_utf8(key.to_vec()).unwrap(),
+            Err(_) => "!".to_string(),
+        }).collect();
+    }
+
+    #[test]
+    fn test_merged_kv_iterator_goes_on_after_error() {
+        let iters : Vec<KvIterator> = vec![
+            Box::new(vec![kv("a"), err(), kv("d"), kv("e")].into_iter()),
+            Box::new(vec![kv("b"), kv("c"), kv("d")].into_iter()),
+        ];
+        assert_eq!(collect_keys(MergedKvIterator::new(iters)), vec!["a", "!", "b", "c", "d", "e"]);
+
+        //the error of the first record.
+        let iters : Vec<KvIterator> = vec![
+            Box::new(vec![err(), kv("b")].into_iter()),
+            Box::new(vec![kv("a"), err(), kv("c")].into_iter()),
+        ];
+        assert_eq!(collect_keys(MergedKvIterator::new(iters)), vec!["!", "a", "!", "b", "c"]);
+    }
+
+    #[test]
+    fn test_overlay_kv_iterator_goes_on_after_error() {
+        let base : KvIterator = Box::new(vec![kv("a"), err(), kv("c"), kv("d")].into_iter());
+        let changes : Vec<KvChange> = vec![
+            (Box::from(b"b".as_slice()), Some(Box::from(b"b".as_slice()))),
+            (Box::from(b"c".as_slice()), None),
+        ];
+        assert_eq!(collect_keys(OverlayKvIterator::new(base, changes)), vec!["a", "!", "b", "d"]);
+    }
+}
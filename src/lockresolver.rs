@@ -0,0 +1,203 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::{errors::Error, kviter::KvPair, storagenode::RocksDbStorageNode};
+use txn_types::{Key, Lock, LockType, TimeStamp, Write, WriteRef, WriteType};
+
+#[derive(Debug, Clone)]
+pub enum LockStatus {
+    Committed(TimeStamp),
+    RolledBack,
+    //can't be decided by the data of this node, such as the primary key is not in it.
+    Unknown(String),
+}
+
+impl fmt::Display for LockStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LockStatus::Committed(commit_ts) => write!(f, "committed at {}", commit_ts),
+            LockStatus::RolledBack => write!(f, "rolled back"),
+            LockStatus::Unknown(reason) => write!(f, "unknown, {}", reason),
+        }
+    }
+}
+
+pub struct LockInfo {
+    //the raw key.
+    pub key : Box<[u8]>,
+    pub lock : Lock,
+    pub status : LockStatus,
+}
+
+//decides the pending locks of the lock cf offline as the resolver of TiKV does,
+//checks the primary key, and the secondaries for the async-commit transactions.
+//the transactions committed by 1PC never leave locks, they are in cf write already.
+pub struct LockResolver<'a> {
+    node : &'a RocksDbStorageNode,
+    //(primary key, start_ts) -> status of the transaction.
+    txn_status_cache : HashMap<(Vec<u8>, TimeStamp), LockStatus>,
+}
+
+impl <'a> LockResolver<'a> {
+    pub fn new(node : &'a RocksDbStorageNode) -> LockResolver<'a> {
+        return LockResolver {
+            node,
+            txn_status_cache : HashMap::new(),
+        };
+    }
+
+    //the locks in [lower_bound, upper_bound) of the lock cf, the bounds are data keys without ts.
+    pub fn scan_locks(&mut self, lower_bound : &[u8], upper_bound : &[u8]) -> Result<Vec<LockInfo>, Error> {
+        let mut ret = vec![];
+        for item_res in self.node.get_region_cf_iter("lock", lower_bound, upper_bound) {
+            let (key_data, val_data) = item_res?;
+            let lock = match Lock::parse(val_data.as_ref()) {
+                Ok(l) => l,
+                Err(_) => return Err(Error::CorruptedDataBytes("parse Lock error.".to_string(), val_data)),
+            };
+            let key = decode_data_key(key_data)?;
+            let status = self.get_lock_status(&key, &lock)?;
+            ret.push(LockInfo { key, lock, status });
+        }
+
+        return Ok(ret);
+    }
+
+    //the write records of the committed locks in the range, which are in the order of cf write.
+    pub fn get_committed_writes(&mut self, lower_bound : &[u8], upper_bound : &[u8]) -> Result<Vec<KvPair>, Error> {
+        let mut ret : Vec<KvPair> = vec![];
+        for lock_info in self.scan_locks(lower_bound, upper_bound)? {
+            let commit_ts = match lock_info.status {
+                LockStatus::Committed(ts) => ts,
+                _ => continue,
+            };
+            let write_type = match lock_info.lock.lock_type {
+                LockType::Put => WriteType::Put,
+                LockType::Delete => WriteType::Delete,
+                LockType::Lock | LockType::Pessimistic => continue,
+            };
+
+            let write = Write::new(write_type, lock_info.lock.ts, lock_info.lock.short_value.clone());
+            let write_key = keys::data_key(Key::from_raw(&lock_info.key).append_ts(commit_ts).as_encoded());
+            ret.push((write_key.into_boxed_slice(), write.as_ref().to_bytes().into_boxed_slice()));
+        }
+
+        return Ok(ret);
+    }
+
+    fn get_lock_status(&mut self, key : &[u8], lock : &Lock) -> Result<LockStatus, Error> {
+        //pessimistic locks carry no data, they are replaced by the prewrites when committing.
+        if lock.lock_type == LockType::Pessimistic {
+            return Ok(LockStatus::RolledBack);
+        }
+
+        //committed or rolled back already, but the lock is not cleaned up yet.
+        if let Some(status) = self.get_commit_record_status(key, lock.ts)? {
+            return Ok(status);
+        }
+
+        let cache_key = (lock.primary.clone(), lock.ts);
+        if let Some(status) = self.txn_status_cache.get(&cache_key) {
+            return Ok(status.clone());
+        }
+
+        let status = self.get_txn_status(&lock.primary, lock.ts)?;
+        self.txn_status_cache.insert(cache_key, status.clone());
+        return Ok(status);
+    }
+
+    fn get_txn_status(&mut self, primary : &[u8], start_ts : TimeStamp) -> Result<LockStatus, Error> {
+        if let Some(status) = self.get_commit_record_status(primary, start_ts)? {
+            return Ok(status);
+        }
+
+        let primary_lock = match self.get_lock(primary)? {
+            Some(l) if l.ts == start_ts => l,
+            _ => return Ok(LockStatus::Unknown(format!("primary key {} is not found", hex::encode_upper(primary)))),
+        };
+
+        //the primary is not committed, the prewrites of 2PC transactions will be rolled back.
+        if !primary_lock.use_async_commit {
+            return Ok(LockStatus::RolledBack);
+        }
+
+        //an async-commit transaction is committed if all the keys are prewritten,
+        //and the commit ts is the max min_commit_ts of them.
+        let mut commit_ts = primary_lock.min_commit_ts;
+        for secondary in primary_lock.secondaries.iter() {
+            if let Some(status) = self.get_commit_record_status(secondary, start_ts)? {
+                return Ok(status);
+            }
+
+            match self.get_lock(secondary)? {
+                Some(l) if l.ts == start_ts => {
+                    if l.min_commit_ts > commit_ts {
+                        commit_ts = l.min_commit_ts;
+                    }
+                },
+                _ => return Ok(LockStatus::Unknown(format!("secondary key {} is not found", hex::encode_upper(secondary)))),
+            }
+        }
+
+        return Ok(LockStatus::Committed(commit_ts));
+    }
+
+    fn get_lock(&self, key : &[u8]) -> Result<Option<Lock>, Error> {
+        let lower_bound = keys::data_key(Key::from_raw(key).as_encoded());
+        let mut upper_bound = lower_bound.clone();
+        upper_bound.push(0);
+
+        return match self.node.get_cf_iter("lock", &lower_bound, &upper_bound)?.next() {
+            None => Ok(None),
+            Some(res) => {
+                let (_, val_data) = res?;
+                match Lock::parse(val_data.as_ref()) {
+                    Ok(l) => Ok(Some(l)),
+                    Err(_) => Err(Error::CorruptedDataBytes("parse Lock error.".to_string(), val_data)),
+                }
+            },
+        };
+    }
+
+    //finds the write record of the transaction started at start_ts in cf write.
+    fn get_commit_record_status(&self, key : &[u8], start_ts : TimeStamp) -> Result<Option<LockStatus>, Error> {
+        //the commit ts is not less than the start ts, and the rollback record is written at the start ts.
+        let lower_bound = keys::data_key(Key::from_raw(key).append_ts(TimeStamp::max()).as_encoded());
+        let upper_bound = keys::data_key(Key::from_raw(key).append_ts(start_ts.prev()).as_encoded());
+
+        for item_res in self.node.get_cf_iter("write", &lower_bound, &upper_bound)? {
+            let (key_data, val_data) = item_res?;
+            let commit_ts = match Key::decode_ts_from(key_data.as_ref()) {
+                Ok(ts) => ts,
+                Err(_) => return Err(Error::CorruptedDataBytes("cf write key decode ts error.".to_string(), key_data)),
+            };
+            let write_ref = match WriteRef::parse(val_data.as_ref()) {
+                Ok(r) => r,
+                Err(_) => return Err(Error::CorruptedDataBytes("parse WriteRef error.".to_string(), val_data)),
+            };
+
+            //the rollback is collapsed into the commit record of another transaction.
+            if commit_ts == start_ts && write_ref.has_overlapped_rollback {
+                return Ok(Some(LockStatus::RolledBack));
+            }
+            if write_ref.start_ts != start_ts {
+                continue;
+            }
+
+            if write_ref.write_type == WriteType::Rollback {
+                return Ok(Some(LockStatus::RolledBack));
+            }
+            return Ok(Some(LockStatus::Committed(commit_ts)));
+        }
+
+        return Ok(None);
+    }
+}
+
+fn decode_data_key(key_data : Box<[u8]>) -> Result<Box<[u8]>, Error> {
+    let mut encoded_key_ref = key_data.get(1..).unwrap_or_default();
+    return match tikv_util::codec::bytes::decode_bytes(&mut encoded_key_ref, false) {
+        Ok(k) => Ok(k.into_boxed_slice()),
+        Err(_) => Err(Error::CorruptedDataBytes("key data decode error.".to_string(), key_data)),
+    };
+}
@@ -5,30 +5,64 @@ mod tidbtypes;
 mod tabledataiterator;
 mod datum;
 mod export;
+mod kviter;
+mod sst;
+mod titan;
+mod mvcc;
+mod lockresolver;
+mod region;
+mod report;
+mod schemadiff;
+mod indexdataiterator;
+mod indexcheck;
+mod indexrebuild;
+mod quarantine;
+mod backup;
+mod raftlog;
+mod tablestats;
+mod rowfilter;
 
-use std::{sync::{Arc, atomic::AtomicBool}, thread};
+use std::{sync::{Arc, Mutex, atomic::AtomicBool}, thread, path::{Path, PathBuf}};
 
-use clap::{Parser, builder::ArgPredicate};
+use clap::Parser;
 use export::{exporter::TiDBExporter, CsvExporter};
+use txn_types::TimeStamp;
+use chrono::TimeZone;
 
 
-use crate::{storagenode::RocksDbStorageNode, tidbtypes::TableInfo};
+use crate::{storagenode::RocksDbStorageNode, tidbtypes::{TableInfo, TableInfoVersion, IndexInfo}, datum::RowData, indexrebuild::{IndexRowRebuilder, RebuiltRow}, lockresolver::LockResolver, report::ReportWriter, quarantine::Quarantine, rowfilter::RowSelection};
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Cli {
-    ///rocksdb directory path of using by TiDB.
-    #[arg(short, long)]
-    path : String,
+    ///rocksdb directory path of using by TiDB. several paths of the stores in the same cluster can be specified, the data of them will be merged.
+    #[arg(short, long, num_args = 1.., required = true)]
+    path : Vec<String>,
+
+    ///how the paths are read. 'db': open them as rocksdb directories. 'sst': read the loose sst files in them without opening the db, for the directories whose MANIFEST or CURRENT is damaged. 'repair': copy them to --work-dir and open the copies repaired by rocksdb. 'br': read them as the local directories of BR backups. 'tablets': read them as the tablets directories of the partitioned raft kv.
+    #[arg(long, value_names(["db", "sst", "repair", "br", "tablets"]), default_value = "db")]
+    source : String,
+
+    ///the directory which the stores are copied to and repaired in for '--source repair', it must not exist. raft-engine is also copied to it if it's specified.
+    #[arg(long, required_if_eq("source", "repair"))]
+    work_dir : Option<String>,
+
+    ///id of the keyspace to read for the clusters with API v2 and keyspaces, the keys of TiDB in it are prefixed with 'x' and the id. the keyspaces are listed by '--mode keyspaces'.
+    #[arg(long)]
+    keyspace : Option<u32>,
 
     ///database name for listing tables or exporting.
     #[arg(short, long)]
     database : Option<String>,
 
     ///table name that need to be exported, must be in the database specified by --database
-    #[arg(short, long, requires_if(ArgPredicate::IsPresent, "exporter"))]
+    #[arg(short, long)]
     table : Option<String>,
 
+    ///id of the table that need to be exported instead of --table, it can be a dropped or truncated table listed by '--mode dropped'.
+    #[arg(long, conflicts_with = "table")]
+    table_id : Option<i64>,
+
     ///the exporter that the data will be written to. only support 'csv' for now.
     #[arg(short, long, value_names(["csv"]))]
     exporter : Option<String>,
@@ -48,6 +82,50 @@ struct Cli {
     #[arg(short = 'n', long, default_value_t = 3)]
     thread_num : usize,
 
+    ///split the data of the table into this number of ranges by the boundaries of the regions and the sst files, each range is read by its own thread and written to its own files numbered by the range, such as user.r0001.csv. fewer ranges are used if the table is too small to split.
+    #[arg(long, default_value_t = 1)]
+    scan_ranges : usize,
+
+    ///only read the data committed before or at this moment, both TSO(e.g. 441234567890123456) and local time(e.g. '2023-05-01 12:00:00') are accepted.
+    #[arg(long)]
+    as_of_ts : Option<String>,
+
+    ///include the data of the pending locks in the lock cf whose transactions are committed, checked by the primary key or the secondaries of async-commit transactions.
+    #[arg(long, default_value_t = false)]
+    resolve_locks : bool,
+
+    ///read the data in all the key ranges, including the ones of the regions which are not in Normal state, such as Tombstone or Applying.
+    #[arg(long, default_value_t = false)]
+    all_regions : bool,
+
+    ///other operations instead of exporting. 'locks': list the pending locks and the decisions of them. 'regions': list the regions of the table specified by --table, or all the regions. 'dropped': list the dropped or truncated tables of the database found in the ddl history. 'schemas': list the versions of the schema of the table specified by --table and the changes between them. 'check': check the indexes of the table specified by --table with its rows, all the index entries of the table are held in memory. 'rebuild': export the rows of the table rebuilt from its indexes when the records are lost. 'keyspaces': list the ids of the keyspaces which have data. 'stats': count the rows, versions, deletes, rollbacks and locks of the table specified by --table and the bytes in each cf.
+    #[arg(long, value_names(["locks", "regions", "dropped", "schemas", "check", "rebuild", "keyspaces", "stats"]))]
+    mode : Option<String>,
+
+    ///names of the indexes to rebuild the rows from for '--mode rebuild', all the indexes are used by default.
+    #[arg(long, num_args = 1.., value_delimiter = ',')]
+    indexes : Vec<String>,
+
+    ///names of the columns to export in this order, such as 'id,name', all the columns are exported by default.
+    #[arg(long, num_args = 1.., value_delimiter = ',')]
+    columns : Vec<String>,
+
+    ///only export the rows matched by the condition, such as "age >= 18 AND name LIKE 'a%'". comparisons, IN, IS [NOT] NULL, LIKE, NOT, AND, OR and parentheses are supported, the values are compared as numbers if both of them are numbers, otherwise the strings are compared by bytes regardless of the collation of the column, so 'abc' doesn't equal 'ABC' even for the _ci collations.
+    #[arg(long = "where")]
+    where_cond : Option<String>,
+
+    ///what to do when a row can't be read or decoded. 'abort': stop the export. 'skip': log the key and value of the bad record to the quarantine file beside the exported files, and go on with the next row.
+    #[arg(long, value_names(["abort", "skip"]), default_value = "abort")]
+    on_error : String,
+
+    ///replay the committed raft log entries which are not applied yet, such as the last writes before a crash. the entries are read from the raft-engine or raft directory beside each db directory unless --raft-path is specified.
+    #[arg(long, default_value_t = false)]
+    replay_raft : bool,
+
+    ///the raft-engine or raft directories, one for each path in the same order. the region meta are read from raft-engine for the stores which have none in the db, and they are used by '--replay-raft'. they are required by '--source tablets', the raft-engine directory beside each tablets directory is used by default. raft-engine is copied to --work-dir, or a temporary directory, before it's read.
+    #[arg(long, num_args = 1..)]
+    raft_path : Vec<String>,
+
     ///display debug messages.
     #[arg(long, default_value_t = false)]
     debug : bool
@@ -55,14 +133,137 @@ struct Cli {
 
 fn main() {
     let cli = Cli::parse();
-    let rocksdb_node = match RocksDbStorageNode::new(&cli.path) {
+    //the copies are never written into an existing directory.
+    if let Some(work_dir) = cli.work_dir.as_deref() {
+        if Path::new(work_dir).exists() {
+            print!("the work directory {} already exists.\n", work_dir);
+            return;
+        }
+    }
+    let node_res = match cli.source.as_str() {
+        "db" => RocksDbStorageNode::new(&cli.path),
+        "sst" => RocksDbStorageNode::new_from_sst_dirs(&cli.path),
+        "repair" => RocksDbStorageNode::new_repaired(&cli.path, cli.work_dir.as_deref().unwrap_or_default()),
+        "br" => RocksDbStorageNode::new_from_backup_dirs(&cli.path),
+        "tablets" => RocksDbStorageNode::new_from_tablet_dirs(&cli.path),
+        _ => {
+            print!("unknown source: {}\n", cli.source);
+            return;
+        },
+    };
+    let mut rocksdb_node = match node_res {
         Ok(n) => n,
         Err(e) => {
             print!("{:?}", e);
             return;
         },
     };
+    for store in rocksdb_node.get_stores() {
+        for (path, reason) in store.get_skipped_sst_files() {
+            print!("skip the sst file {}: {}\n", path.display(), reason);
+        }
+    }
+
+    //the tablets keep the data of their parents after splits, which are cut off by the ranges of the regions in raft-engine.
+    if cli.replay_raft || !cli.raft_path.is_empty() || cli.source == "tablets" {
+        if cli.source == "br" {
+            print!("the raft logs are not in the BR backups.\n");
+            return;
+        }
+        let raft_paths : Vec<PathBuf> = if cli.raft_path.is_empty() {
+            cli.path.iter().map(|p| get_default_raft_path(p)).collect()
+        } else {
+            cli.raft_path.iter().map(PathBuf::from).collect()
+        };
+        if cli.source == "tablets" {
+            if let Some(raft_path) = raft_paths.iter().find(|p| !p.is_dir()) {
+                print!("the region meta of the tablets are not found in {}, specify the raft-engine directories by --raft-path.\n", raft_path.display());
+                return;
+            }
+        }
+        match rocksdb_node.load_raft_logs(&raft_paths, cli.work_dir.as_deref(), cli.replay_raft) {
+            Ok(summaries) => {
+                for (raft_path, summary) in raft_paths.iter().zip(summaries) {
+                    print!("replay {} entries of {} regions in {}, {} puts, {} deletes, {} skipped.\n",
+                        summary.entry_num, summary.region_num, raft_path.display(), summary.put_num, summary.delete_num, summary.skipped_num);
+                    if summary.missing_entry_num > 0 {
+                        print!("{} committed entries are not found in {}.\n", summary.missing_entry_num, raft_path.display());
+                    }
+                }
+            },
+            Err(e) => {
+                print!("{}", e.to_string());
+                if cli.debug {
+                    errors::display_corrupted_err_data(&e);
+                }
+                return;
+            },
+        }
+    }
+
+    if let Some(keyspace_id) = cli.keyspace {
+        if keyspace_id > storagenode::MAX_KEYSPACE_ID {
+            print!("invalid keyspace id: {}, it must not be larger than {}.\n", keyspace_id, storagenode::MAX_KEYSPACE_ID);
+            return;
+        }
+        rocksdb_node.set_keyspace(keyspace_id);
+    }
 
+    if let Some(as_of_ts) = cli.as_of_ts.as_ref() {
+        match parse_read_ts(as_of_ts) {
+            Ok(ts) => rocksdb_node.set_read_ts(ts),
+            Err(e) => {
+                print!("{}\n", e);
+                return;
+            }
+        }
+    }
+
+
+    rocksdb_node.set_resolve_locks(cli.resolve_locks);
+    if !cli.all_regions {
+        if let Err(e) = rocksdb_node.limit_to_normal_regions() {
+            print!("{}", e.to_string());
+            if cli.debug {
+                errors::display_corrupted_err_data(&e);
+            }
+            return;
+        }
+    }
+
+    if cli.on_error != "abort" && cli.on_error != "skip" {
+        print!("unknown value of --on-error: {}\n", cli.on_error);
+        return;
+    }
+
+    if let Some(mode) = cli.mode.as_ref() {
+        match mode.as_str() {
+            "locks" => {
+                print_locks(&rocksdb_node, cli.debug);
+                return;
+            },
+            "regions" => if cli.table.is_none() && cli.table_id.is_none() {
+                print_regions(&rocksdb_node, None, cli.debug);
+                return;
+            },
+            "keyspaces" => {
+                print_keyspaces(&rocksdb_node, cli.debug);
+                return;
+            },
+            "dropped" | "schemas" | "check" | "stats" => (),
+            "rebuild" => if cli.exporter.is_none() {
+                print!("the exporter must be specified by --exporter for rebuilding a table.\n");
+                return;
+            },
+            _ => {
+                print!("unknown mode: {}\n", mode);
+                return;
+            },
+        }
+    } else if (cli.table.is_some() || cli.table_id.is_some()) && cli.exporter.is_none() {
+        print!("the exporter must be specified by --exporter for exporting a table.\n");
+        return;
+    }
 
     if cli.database.is_none() {
         print_databases(&rocksdb_node, cli.debug);
@@ -88,13 +289,17 @@ fn main() {
         return;
     }
 
+    let db_id = db_info_opt.unwrap().id;
+    if cli.mode.as_deref() == Some("dropped") {
+        print_dropped_tables(&rocksdb_node, db_id, cli.debug);
+        return;
+    }
 
-    if cli.table.is_none() {
-        print_tables(&rocksdb_node, db_info_opt.unwrap().id, cli.debug);
+    if cli.table.is_none() && cli.table_id.is_none() {
+        print_tables(&rocksdb_node, db_id, cli.debug);
         return;
     }
 
-    let db_id = db_info_opt.unwrap().id;
     let tables = match rocksdb_node.get_table_info_by_dbid(db_id) {
         Ok(t) => t,
         Err(e) => {
@@ -106,15 +311,32 @@ fn main() {
         }
     };
 
-    let table_name = cli.table.as_ref().unwrap();
-    let table_info_opt = tables.iter().find(|&t| t.name.L.eq(table_name));
+    let dropped_table_info : Option<TableInfo>;
+    let table_info_opt = match cli.table_id {
+        Some(table_id) => match tables.iter().find(|&t| t.id == table_id) {
+            Some(t) => Some(t),
+            None => {
+                dropped_table_info = get_dropped_table_info(&rocksdb_node, db_id, table_id, cli.debug);
+                dropped_table_info.as_ref()
+            },
+        },
+        None => tables.iter().find(|&t| t.name.L.eq(cli.table.as_ref().unwrap())),
+    };
     if table_info_opt.is_none() {
-        print!("not fount table: {}\n", table_name);
+        match cli.table_id {
+            Some(table_id) => print!("not fount table: {}\n", table_id),
+            None => print!("not fount table: {}\n", cli.table.as_ref().unwrap()),
+        }
         print_tables(&rocksdb_node, db_id, cli.debug);
         return;
     }
 
     let original_table_info = table_info_opt.unwrap();
+    if cli.mode.as_deref() == Some("schemas") {
+        print_table_schema_history(&rocksdb_node, db_id, original_table_info.id, cli.debug);
+        return;
+    }
+
     let mut table_infos : Vec<&TableInfo> = Vec::new();
     let partition_table_infos : Vec<TableInfo>;
     if original_table_info.have_partitions() {
@@ -124,13 +346,166 @@ fn main() {
         table_infos.push(original_table_info);
     }
 
+    if cli.mode.as_deref() == Some("regions") {
+        for table_info in table_infos {
+            print_regions(&rocksdb_node, Some(table_info), cli.debug);
+        }
+        return;
+    }
+
+    if cli.mode.as_deref() == Some("stats") {
+        for table_info in table_infos {
+            print_table_stats(&rocksdb_node, table_info, cli.debug);
+        }
+        return;
+    }
+
+    if cli.mode.as_deref() == Some("check") {
+        for table_info in table_infos {
+            print_index_check(&rocksdb_node, table_info, cli.debug);
+        }
+        return;
+    }
+
+    if cli.mode.as_deref() == Some("rebuild") {
+        //the rows which are not found in some of the indexes, the columns only in them are NULL.
+        let rebuild_report = match ReportWriter::new(cli.write_path.as_deref().unwrap_or_default(), "rebuild", &["handle", "missing_in"]) {
+            Ok(r) => Arc::new(Mutex::new(r)),
+            Err(e) => {
+                print!("{}", e.to_string());
+                return;
+            },
+        };
+        let quarantine = match create_quarantine(&cli) {
+            Ok(q) => q,
+            Err(e) => {
+                print!("{}", e.to_string());
+                return;
+            },
+        };
+
+        for table_info in table_infos {
+            rebuild_data(&rocksdb_node, table_info, &cli, rebuild_report.clone(), quarantine.clone());
+        }
+
+        if let Ok(mut report) = rebuild_report.lock() {
+            _ = report.flush();
+        }
+        if let Some(q) = quarantine {
+            print_quarantine_summary(&q);
+        }
+        return;
+    }
+
+    //the rows written by the old versions of the schema are decoded by them.
+    let schema_versions = match rocksdb_node.get_table_info_history(db_id, original_table_info.id) {
+        Ok(v) => v,
+        Err(e) => {
+            print!("{}", e.to_string());
+            if cli.debug {
+                errors::display_corrupted_err_data(&e);
+            }
+            return;
+        }
+    };
+
+    //the reports are shared by the partitions, which are exported to the same write path.
+    //the rows which are not found in some of the stores, only for merging several stores.
+    let replica_report = if rocksdb_node.get_stores().len() > 1 {
+        match ReportWriter::new(cli.write_path.as_deref().unwrap_or_default(), "replicas", &["handle", "commit_ts", "found_in", "missing_in"]) {
+            Ok(r) => Some(Arc::new(Mutex::new(r))),
+            Err(e) => {
+                print!("{}", e.to_string());
+                return;
+            },
+        }
+    } else {
+        None
+    };
+    let quarantine = match create_quarantine(&cli) {
+        Ok(q) => q,
+        Err(e) => {
+            print!("{}", e.to_string());
+            return;
+        },
+    };
+
     let rn_arc = Arc::new(rocksdb_node);
     for table_info in table_infos {
-        export_data(rn_arc.clone(), table_info, &cli);
+        export_data(rn_arc.clone(), table_info, schema_versions.clone(), &cli, replica_report.clone(), quarantine.clone());
+    }
+
+    if let Some(report) = replica_report {
+        if let Ok(mut report) = report.lock() {
+            _ = report.flush();
+            if report.get_record_num() > 0 {
+                print!("{} rows are only found in some of the stores, see {}\n", report.get_record_num(), report.get_path().display());
+            }
+        }
+    }
+    if let Some(q) = quarantine {
+        print_quarantine_summary(&q);
     }
 }
 
 
+//TSO, or local time which will be composed to a TSO with the logical part of 0.
+fn parse_read_ts(ts_str : &str) -> Result<TimeStamp, String> {
+    if let Ok(ts) = ts_str.trim().parse::<u64>() {
+        return Ok(TimeStamp::new(ts));
+    }
+
+    let naive_time = match chrono::NaiveDateTime::parse_from_str(ts_str.trim(), "%Y-%m-%d %H:%M:%S") {
+        Ok(t) => t,
+        Err(e) => return Err(format!("invalid as-of-ts: {}, {}", ts_str, e.to_string())),
+    };
+    let local_time = match naive_time.and_local_timezone(chrono::Local) {
+        chrono::LocalResult::Single(t) => t,
+        chrono::LocalResult::Ambiguous(t, _) => t,
+        chrono::LocalResult::None => return Err(format!("invalid as-of-ts: {}, not exists in local timezone", ts_str)),
+    };
+
+    let physical = local_time.timestamp_millis();
+    if physical < 0 {
+        return Err(format!("invalid as-of-ts: {}", ts_str));
+    }
+
+    return Ok(TimeStamp::compose(physical as u64, 0));
+}
+
+//the raft directory is beside the db directory in the data directory of TiKV,
+//it's raft-engine since TiKV 6.x.
+fn get_default_raft_path(db_path : &str) -> PathBuf {
+    let path = Path::new(db_path);
+    let data_dir = path.parent().unwrap_or(path);
+    let raft_engine_path = data_dir.join("raft-engine");
+    if raft_engine_path.is_dir() {
+        return raft_engine_path;
+    }
+    return data_dir.join("raft");
+}
+
+
+fn print_keyspaces(rocksdb_node : &RocksDbStorageNode, is_debug : bool) {
+    match rocksdb_node.get_keyspaces() {
+        Ok(keyspace_ids) => {
+            if keyspace_ids.is_empty() {
+                print!("no keyspace found, the data is not in API v2 keyspaces.\n");
+            }
+            for keyspace_id in keyspace_ids {
+                print!("{}\n", keyspace_id);
+            }
+        },
+        Err(e) => {
+            print!("{}", e.to_string());
+            if is_debug {
+                errors::display_corrupted_err_data(&e);
+            }
+            return;
+        },
+    };
+}
+
 fn print_databases(rocksdb_node : &RocksDbStorageNode, is_debug : bool) {
     match rocksdb_node.get_databases() {
         Ok(db_info_vec) => {
@@ -166,25 +541,324 @@ fn print_tables(rocksdb_node : &RocksDbStorageNode, db_id : i64, is_debug : bool
     
 }
 
+fn print_locks(rocksdb_node : &RocksDbStorageNode, is_debug : bool) {
+    let mut resolver = LockResolver::new(rocksdb_node);
+    match resolver.scan_locks(keys::DATA_MIN_KEY, keys::DATA_MAX_KEY) {
+        Ok(lock_infos) => {
+            for lock_info in lock_infos {
+                print!("{}, {:?}, start_ts: {}, primary: {}, async-commit: {}, {}\n",
+                    hex::encode_upper(&lock_info.key),
+                    lock_info.lock.lock_type,
+                    lock_info.lock.ts,
+                    hex::encode_upper(&lock_info.lock.primary),
+                    lock_info.lock.use_async_commit,
+                    lock_info.status);
+            }
+        },
+        Err(e) => {
+            print!("{}", e.to_string());
+            if is_debug {
+                errors::display_corrupted_err_data(&e);
+            }
+            return;
+        },
+    };
+}
+
+fn print_dropped_tables(rocksdb_node : &RocksDbStorageNode, db_id : i64, is_debug : bool) {
+    let dropped_tables = match rocksdb_node.get_dropped_tables(db_id) {
+        Ok(t) => t,
+        Err(e) => {
+            print!("{}", e.to_string());
+            if is_debug {
+                errors::display_corrupted_err_data(&e);
+            }
+            return;
+        },
+    };
+
+    for dropped_table in dropped_tables {
+        let job = &dropped_table.job;
+        let (action, new_table) = if job.action_type == tidbtypes::ActionTruncateTable {
+            match job.get_table_info() {
+                Some(t) => ("truncated", format!(", new table id: {}", t.id)),
+                None => ("truncated", String::new()),
+            }
+        } else {
+            ("dropped", String::new())
+        };
+        let table_name = match dropped_table.table_info.as_ref() {
+            Some(t) => t.name.L.clone(),
+            None => job.table_name.clone(),
+        };
+        let schema_state = if dropped_table.table_info.is_some() { "" } else { ", schema not found" };
+
+        print!("{}, {}, {} at {}{}, job: {}{}, query: {}\n",
+            job.table_id,
+            table_name,
+            action,
+            format_ts(TimeStamp::new(job.get_finished_ts())),
+            new_table,
+            job.id,
+            schema_state,
+            job.query);
+    }
+}
+
+fn print_table_schema_history(rocksdb_node : &RocksDbStorageNode, db_id : i64, table_id : i64, is_debug : bool) {
+    let versions = match rocksdb_node.get_table_info_history(db_id, table_id) {
+        Ok(v) => v,
+        Err(e) => {
+            print!("{}", e.to_string());
+            if is_debug {
+                errors::display_corrupted_err_data(&e);
+            }
+            return;
+        },
+    };
+
+    let mut prev_table_info : Option<&TableInfo> = None;
+    for version in versions.iter() {
+        let table_info = &version.table_info;
+        print!("commit_ts: {} ({}), update_timestamp: {}, state: {}\n",
+            version.commit_ts,
+            format_ts(TimeStamp::new(version.commit_ts)),
+            table_info.update_timestamp,
+            schemadiff::format_schema_state(table_info.state));
+
+        let lines = match prev_table_info {
+            Some(prev) => schemadiff::diff_table_info(prev, table_info),
+            None => schemadiff::describe_table_info(table_info),
+        };
+        if lines.is_empty() {
+            print!("    no changes of columns and indexes\n");
+        }
+        for line in lines {
+            print!("    {}\n", line);
+        }
+        prev_table_info = Some(table_info);
+    }
+}
+
+fn print_index_check(rocksdb_node : &RocksDbStorageNode, table_info : &TableInfo, is_debug : bool) {
+    print!("table: {}, {}\n", table_info.id, table_info.name.L);
+    let checkable_indexes = indexcheck::get_checkable_indexes(table_info);
+    for index_info in table_info.index_info.iter() {
+        if !checkable_indexes.iter().any(|i| i.id == index_info.id) {
+            print!("index {} is skipped, it's the clustered primary key, a global or expression index, or not public.\n", index_info.idx_name.O);
+        }
+    }
+
+    let mut on_problem = |problem : &indexcheck::IndexProblem| {
+        print!("{}, {}, handle: {}, index: ({}), row: ({})\n",
+            problem.index_info.idx_name.O,
+            problem.kind,
+            indexcheck::format_handle(table_info, &problem.handle),
+            problem.index_values.join(", "),
+            problem.row_values.join(", "));
+    };
+
+    match indexcheck::check_table(rocksdb_node, table_info, &mut on_problem) {
+        Ok(summaries) => {
+            for summary in summaries {
+                print!("index {}: {} entries, {} missing, {} dangling, {} mismatched\n",
+                    summary.index_info.idx_name.O,
+                    summary.entry_num,
+                    summary.missing_num,
+                    summary.dangling_num,
+                    summary.mismatch_num);
+            }
+        },
+        Err(e) => {
+            print!("{}", e.to_string());
+            if is_debug {
+                errors::display_corrupted_err_data(&e);
+            }
+        },
+    }
+}
+
+fn print_table_stats(rocksdb_node : &RocksDbStorageNode, table_info : &TableInfo, is_debug : bool) {
+    print!("table: {}, {}\n", table_info.id, table_info.name.L);
+    let stats = match tablestats::collect_table_stats(rocksdb_node, table_info) {
+        Ok(s) => s,
+        Err(e) => {
+            print!("{}", e.to_string());
+            if is_debug {
+                errors::display_corrupted_err_data(&e);
+            }
+            return;
+        },
+    };
+
+    print!("rows: {}\n", stats.row_num);
+    print!("versions: {}, puts: {}, deletes: {}, rollbacks: {}, lock records: {}\n",
+        stats.version_num, stats.put_num, stats.delete_num, stats.rollback_num, stats.lock_record_num);
+    print!("pending locks: {}\n", stats.lock_num);
+    print!("bytes: default {}(logical), write {}, lock {}\n", stats.default_bytes, stats.write_bytes, stats.lock_bytes);
+    if let (Some(min_handle), Some(max_handle)) = (stats.min_handle.as_ref(), stats.max_handle.as_ref()) {
+        print!("handle: {} - {}\n", indexcheck::format_handle(table_info, min_handle), indexcheck::format_handle(table_info, max_handle));
+    }
+    if let (Some(oldest_ts), Some(newest_ts)) = (stats.oldest_commit_ts, stats.newest_commit_ts) {
+        print!("commit ts: {}({}) - {}({})\n", oldest_ts, format_ts(oldest_ts), newest_ts, format_ts(newest_ts));
+    }
+    if stats.after_read_ts_num > 0 {
+        print!("puts and deletes committed after the read ts: {}\n", stats.after_read_ts_num);
+    }
+}
+
+fn get_dropped_table_info(rocksdb_node : &RocksDbStorageNode, db_id : i64, table_id : i64, is_debug : bool) -> Option<TableInfo> {
+    let dropped_tables = match rocksdb_node.get_dropped_tables(db_id) {
+        Ok(t) => t,
+        Err(e) => {
+            print!("{}", e.to_string());
+            if is_debug {
+                errors::display_corrupted_err_data(&e);
+            }
+            return None;
+        },
+    };
+
+    let dropped_table = dropped_tables.into_iter().rev().find(|t| t.job.table_id == table_id)?;
+    if dropped_table.table_info.is_none() {
+        print!("the schema of the dropped table {} is not found.\n", table_id);
+    }
+    return dropped_table.table_info;
+}
+
+//the local time of the physical part of a TSO.
+fn format_ts(ts : TimeStamp) -> String {
+    return match chrono::Local.timestamp_millis_opt(ts.physical() as i64) {
+        chrono::LocalResult::Single(t) | chrono::LocalResult::Ambiguous(t, _) => t.format("%Y-%m-%d %H:%M:%S").to_string(),
+        chrono::LocalResult::None => ts.to_string(),
+    };
+}
+
+//the regions overlapped with the data of the table, or all the regions if no table specified.
+fn print_regions(rocksdb_node : &RocksDbStorageNode, table_info_opt : Option<&TableInfo>, is_debug : bool) {
+    if let Some(table_info) = table_info_opt {
+        print!("table: {}, {}\n", table_info.id, table_info.name.L);
+    }
+
+    for store in rocksdb_node.get_stores() {
+        let res = store.get_store_id().and_then(|store_id| {
+            Ok((store_id, store.get_regions()?))
+        });
+        let (store_id, regions) = match res {
+            Ok(r) => r,
+            Err(e) => {
+                print!("{}", e.to_string());
+                if is_debug {
+                    errors::display_corrupted_err_data(&e);
+                }
+                return;
+            },
+        };
+
+        match store_id {
+            Some(id) => print!("store: {}, {}\n", id, store.get_path().display()),
+            None => print!("store: unknown, {}\n", store.get_path().display()),
+        }
+        print_store_regions(rocksdb_node, regions, table_info_opt);
+    }
+}
+
+fn print_store_regions(rocksdb_node : &RocksDbStorageNode, regions : Vec<region::RegionInfo>, table_info_opt : Option<&TableInfo>) {
+    for region_info in regions {
+        if let Some(table_info) = table_info_opt {
+            let (lower_bound, upper_bound) = rocksdb_node.get_table_data_keys_by_table_id(table_info.id);
+            if !region_info.is_overlapped(&lower_bound, &upper_bound) {
+                continue;
+            }
+        }
+
+        let region = region_info.local_state.get_region();
+        let peers : Vec<String> = region.get_peers().iter()
+            .map(|p| format!("{}@{}({:?})", p.get_id(), p.get_store_id(), p.get_role()))
+            .collect();
+        let apply_info = match region_info.apply_state.as_ref() {
+            Some(a) => format!("applied_index: {}, commit_index: {}, truncated_index: {}", a.get_applied_index(), a.get_commit_index(), a.get_truncated_state().get_index()),
+            None => "no apply state".to_string(),
+        };
+
+        print!("{}, [{}, {}), conf_ver: {}, version: {}, peers: [{}], state: {:?}, {}\n",
+            region_info.get_id(),
+            hex::encode_upper(region.get_start_key()),
+            hex::encode_upper(region.get_end_key()),
+            region.get_region_epoch().get_conf_ver(),
+            region.get_region_epoch().get_version(),
+            peers.join(", "),
+            region_info.get_state(),
+            apply_info);
+    }
+}
+
+
+fn export_data(rocksdb_node : Arc<RocksDbStorageNode>, table_info : &TableInfo, schema_versions : Vec<TableInfoVersion>, cli : &Cli, replica_report : Option<Arc<Mutex<ReportWriter>>>, quarantine : Option<Arc<Quarantine>>) {
+    let ranges = if cli.scan_ranges > 1 {
+        match rocksdb_node.get_table_data_split_ranges(table_info, cli.scan_ranges) {
+            Ok(r) => r,
+            Err(e) => {
+                print!("{}", e.to_string());
+                if cli.debug {
+                    errors::display_corrupted_err_data(&e);
+                }
+                return;
+            },
+        }
+    } else {
+        vec![rocksdb_node.get_table_data_keys_by_table_id(table_info.id)]
+    };
+    if cli.scan_ranges > 1 {
+        print!("table {} is scanned in {} ranges.\n", table_info.name.L, ranges.len());
+    }
 
-fn export_data(rocksdb_node : Arc<RocksDbStorageNode>, table_info : &TableInfo, cli : &Cli) {
-    let (tx, rx) = crossbeam_channel::bounded(10);
     let is_panic = Arc::new(AtomicBool::new(false));
-    let transmitter_handler;
-    {
+    let selection = match create_row_selection(cli, table_info) {
+        Ok(s) => s,
+        Err(e) => {
+            print!("{}\n", e);
+            return;
+        },
+    };
+
+    //each range is read by its own thread and written to its own files by a single thread,
+    //so the rows in the files are in the order of keys.
+    let range_num = ranges.len();
+    let mut transmitter_handlers = Vec::with_capacity(range_num);
+    let mut export_handlers = vec![];
+    for (i, (lower_bound, upper_bound)) in ranges.into_iter().enumerate() {
+        let (tx, rx) = crossbeam_channel::bounded(10);
         let rd_node = rocksdb_node.clone();
         let table_clone = table_info.clone();
+        let schema_versions = schema_versions.clone();
         let is_debug = cli.debug;
         let is_panic_main = is_panic.clone();
-        transmitter_handler = thread::spawn(move || {
-            if let Ok(data_iterator) = rd_node.get_table_data_iter(&table_clone) {
+        let replica_report = replica_report.clone();
+        let quarantine_thread = quarantine.clone();
+        transmitter_handlers.push(thread::spawn(move || {
+            if let Ok(mut data_iterator) = rd_node.get_table_data_range_iter(&table_clone, &lower_bound, &upper_bound) {
+                data_iterator.set_schema_versions(schema_versions);
+                data_iterator.set_keep_error_records(quarantine_thread.is_some());
                 let rows_block_size : usize = 100;
 
                 let mut rows_block = Vec::with_capacity(rows_block_size);
 
-                for row_data_res in data_iterator {
+                while let Some(row_data_res) = data_iterator.next() {
                     match row_data_res {
                         Ok(row_data) => {
+                            if let Some(report) = replica_report.as_ref() {
+                                let res = match report.lock() {
+                                    Ok(mut r) => write_replica_report(&mut r, &rd_node, &row_data, data_iterator.get_last_sources()),
+                                    Err(_) => Err(errors::Error::Other("the replica report is poisoned.".to_string())),
+                                };
+                                if let Err(e) = res {
+                                    print!("{}", e.to_string());
+                                    is_panic_main.store(true, std::sync::atomic::Ordering::SeqCst);
+                                    return;
+                                }
+                            }
+
                             rows_block.push(row_data);
                             if rows_block.len() == rows_block_size {
                                 if is_panic_main.load(std::sync::atomic::Ordering::SeqCst) {
@@ -196,6 +870,13 @@ fn export_data(rocksdb_node : Arc<RocksDbStorageNode>, table_info : &TableInfo,
                             }
                         },
                         Err(e) => {
+                            if let Some(q) = quarantine_thread.as_ref().filter(|_| errors::is_record_error(&e)) {
+                                let record = data_iterator.take_last_error_record();
+                                match q.add(record.as_ref(), &e) {
+                                    Ok(_) => continue,
+                                    Err(qe) => print!("{}", qe.to_string()),
+                                }
+                            }
                             print!("{}", e.to_string());
                             if is_debug {
                                 errors::display_corrupted_err_data(&e);
@@ -212,38 +893,234 @@ fn export_data(rocksdb_node : Arc<RocksDbStorageNode>, table_info : &TableInfo,
                 panic!("get data iterator failed");
             }
             drop(rd_node);
+        }));
+
+        let write_path = cli.write_path.clone().unwrap_or_default();
+        export_handlers.extend(if range_num > 1 {
+            start_exporter(table_info, cli, &get_range_write_path(&write_path, i + 1), 1, rx, is_panic.clone(), quarantine.clone(), selection.clone())
+        } else {
+            start_exporter(table_info, cli, &write_path, cli.thread_num, rx, is_panic.clone(), quarantine.clone(), selection.clone())
         });
     }
 
-    let thread_num = cli.thread_num;
-    let mut exporter = get_export_writer_by_cli(cli, table_info);
-    exporter.set_thread_num(thread_num);
-    exporter.set_debug_mode(cli.debug);
-
-    let handlers = exporter.start_export(rx.clone(), is_panic.clone());
+    for h in transmitter_handlers {
+        _ = h.join();
+    }
+    for h in export_handlers {
+        _ = h.join();
+    }
+}
 
+//the rows are sent to the exporter by the transmitter thread.
+fn run_exporter(table_info : &TableInfo, cli : &Cli, rx : crossbeam_channel::Receiver<Vec<Box<RowData>>>, is_panic : Arc<AtomicBool>, transmitter_handler : thread::JoinHandle<()>, quarantine : Option<Arc<Quarantine>>, selection : Option<Arc<RowSelection>>) {
+    let write_path = cli.write_path.clone().unwrap_or_default();
+    let handlers = start_exporter(table_info, cli, &write_path, cli.thread_num, rx, is_panic, quarantine, selection);
 
     _ = transmitter_handler.join();
     for h in handlers {
         _ = h.join();
     }
-    drop(rx);
 }
 
-fn get_export_writer_by_cli(cli : &Cli, table_info : &TableInfo) -> Box<dyn TiDBExporter> {
+fn start_exporter(table_info : &TableInfo, cli : &Cli, write_path : &str, thread_num : usize, rx : crossbeam_channel::Receiver<Vec<Box<RowData>>>, is_panic : Arc<AtomicBool>, quarantine : Option<Arc<Quarantine>>, selection : Option<Arc<RowSelection>>) -> Vec<thread::JoinHandle<()>> {
+    let mut exporter = get_export_writer_by_cli(cli, table_info, write_path);
+    exporter.set_thread_num(thread_num);
+    exporter.set_debug_mode(cli.debug);
+    if let Some(q) = quarantine {
+        exporter.set_quarantine(q);
+    }
+    if let Some(s) = selection {
+        exporter.set_row_selection(s);
+    }
+
+    return exporter.start_export(rx, is_panic);
+}
+
+//the files of the range are named by its number, such as user.r0001.csv for user.csv.
+fn get_range_write_path(write_path : &str, range_num : usize) -> String {
+    let path = Path::new(write_path);
+    let mut file_name = format!("{}.r{:04}", path.file_stem().and_then(|s| s.to_str()).unwrap_or_default(), range_num);
+    if let Some(extension) = path.extension().and_then(|s| s.to_str()) {
+        file_name.push('.');
+        file_name.push_str(extension);
+    }
+    return path.with_file_name(file_name).to_string_lossy().to_string();
+}
+
+//the quarantine is only created for '--on-error skip'.
+fn create_quarantine(cli : &Cli) -> Result<Option<Arc<Quarantine>>, errors::Error> {
+    if cli.on_error != "skip" {
+        return Ok(None);
+    }
+    let q = Quarantine::new(cli.write_path.as_deref().unwrap_or_default())?;
+    return Ok(Some(Arc::new(q)));
+}
+
+//the selection is only created for --columns or --where.
+fn create_row_selection(cli : &Cli, table_info : &TableInfo) -> Result<Option<Arc<RowSelection>>, String> {
+    if cli.columns.is_empty() && cli.where_cond.is_none() {
+        return Ok(None);
+    }
+    let selection = RowSelection::new(table_info, &cli.columns, cli.where_cond.as_deref())?;
+    return Ok(Some(Arc::new(selection)));
+}
+
+fn print_quarantine_summary(quarantine : &Quarantine) {
+    if let Err(e) = quarantine.flush() {
+        print!("{}", e.to_string());
+    }
+
+    let error_nums = quarantine.get_error_nums();
+    let skipped_num : usize = error_nums.iter().map(|(_, n)| n).sum();
+    if skipped_num == 0 {
+        print!("no record is skipped by errors.\n");
+        return;
+    }
+    print!("{} records are skipped by errors, see {}\n", skipped_num, quarantine.get_path().display());
+    for (kind, num) in error_nums {
+        print!("    {}: {}\n", kind, num);
+    }
+}
+
+fn rebuild_data(rocksdb_node : &RocksDbStorageNode, table_info : &TableInfo, cli : &Cli, rebuild_report : Arc<Mutex<ReportWriter>>, quarantine : Option<Arc<Quarantine>>) {
+    let indexes : Vec<&IndexInfo> = indexcheck::get_checkable_indexes(table_info).into_iter()
+        .filter(|index_info| cli.indexes.is_empty() || cli.indexes.iter().any(|name| name.to_lowercase() == index_info.idx_name.L))
+        .collect();
+    if indexes.is_empty() {
+        print!("no index of table {} can be used to rebuild the rows.\n", table_info.name.L);
+        return;
+    }
+
+    let mut rebuilder = IndexRowRebuilder::new(table_info, indexes);
+    let index_names : Vec<String> = rebuilder.get_indexes().iter().map(|index_info| index_info.idx_name.O.clone()).collect();
+    print!("rebuild the rows of table {} from the indexes: {}\n", table_info.name.L, index_names.join(", "));
+
+    let recoverable_col_ids = rebuilder.get_recoverable_col_ids();
+    let unrecoverable_cols : Vec<&str> = table_info.cols.iter()
+        .filter(|col| !recoverable_col_ids.contains(&col.id))
+        .map(|col| col.name.O.as_str())
+        .collect();
+    if !unrecoverable_cols.is_empty() {
+        print!("the columns not in the indexes are exported as NULL: {}\n", unrecoverable_cols.join(", "));
+    }
+
+    if let Err(e) = rebuilder.load(rocksdb_node) {
+        print!("{}", e.to_string());
+        if cli.debug {
+            errors::display_corrupted_err_data(&e);
+        }
+        return;
+    }
+    let rows : Vec<(Box<[u8]>, RebuiltRow)> = rebuilder.into_rows().collect();
+
+    let selection = match create_row_selection(cli, table_info) {
+        Ok(s) => s,
+        Err(e) => {
+            print!("{}\n", e);
+            return;
+        },
+    };
+
+    let (tx, rx) = crossbeam_channel::bounded(10);
+    let is_panic = Arc::new(AtomicBool::new(false));
+    let table_clone = table_info.clone();
+    let is_debug = cli.debug;
+    let is_panic_main = is_panic.clone();
+    let transmitter_handler = thread::spawn(move || {
+        let rows_block_size : usize = 100;
+        let mut rows_block = Vec::with_capacity(rows_block_size);
+        let row_num = rows.len();
+        let mut missing_num = 0;
+
+        for (handle, row) in rows {
+            let row_data = match indexrebuild::get_rebuilt_row_data(&table_clone, &handle, &row) {
+                Ok(r) => r,
+                Err(e) => {
+                    print!("{}", e.to_string());
+                    if is_debug {
+                        errors::display_corrupted_err_data(&e);
+                    }
+                    is_panic_main.store(true, std::sync::atomic::Ordering::SeqCst);
+                    return;
+                },
+            };
+
+            if row.found_in.len() < index_names.len() {
+                let missing_in : Vec<&str> = index_names.iter().enumerate()
+                    .filter(|(i, _)| !row.found_in.contains(i))
+                    .map(|(_, name)| name.as_str())
+                    .collect();
+                let res = match rebuild_report.lock() {
+                    Ok(mut r) => r.write_record(&[indexcheck::format_handle(&table_clone, &handle), missing_in.join(";")]),
+                    Err(_) => Err(errors::Error::Other("the rebuild report is poisoned.".to_string())),
+                };
+                if let Err(e) = res {
+                    print!("{}", e.to_string());
+                    is_panic_main.store(true, std::sync::atomic::Ordering::SeqCst);
+                    return;
+                }
+                missing_num += 1;
+            }
+
+            rows_block.push(Box::new(row_data));
+            if rows_block.len() == rows_block_size {
+                if is_panic_main.load(std::sync::atomic::Ordering::SeqCst) {
+                    //somewhere panic
+                    return;
+                }
+                tx.send(rows_block).unwrap();
+                rows_block = Vec::with_capacity(rows_block_size);
+            }
+        }
+        if !rows_block.is_empty() {
+            tx.send(rows_block).unwrap();
+        }
+
+        print!("{} rows are rebuilt.\n", row_num);
+        if missing_num > 0 {
+            if let Ok(r) = rebuild_report.lock() {
+                print!("{} rows are only found in some of the indexes, see {}\n", missing_num, r.get_path().display());
+            }
+        }
+    });
+
+    run_exporter(table_info, cli, rx, is_panic, transmitter_handler, quarantine, selection);
+}
+
+fn write_replica_report(report : &mut ReportWriter, rocksdb_node : &RocksDbStorageNode, row_data : &RowData, sources : &[usize]) -> Result<(), errors::Error> {
+    let missing_stores = rocksdb_node.get_missing_stores(row_data.get_key(), sources);
+    if missing_stores.is_empty() {
+        return Ok(());
+    }
+
+    let stores = rocksdb_node.get_stores();
+    //the source after the stores is the resolved locks.
+    let get_store_name = |idx : &usize| match stores.get(*idx) {
+        Some(store) => store.get_path().display().to_string(),
+        None => "resolved locks".to_string(),
+    };
+
+    return report.write_record(&[
+        hex::encode_upper(row_data.get_handle()),
+        row_data.append_ts.to_string(),
+        sources.iter().map(get_store_name).collect::<Vec<String>>().join(";"),
+        missing_stores.iter().map(get_store_name).collect::<Vec<String>>().join(";"),
+    ]);
+}
+
+fn get_export_writer_by_cli(cli : &Cli, table_info : &TableInfo, write_path : &str) -> Box<dyn TiDBExporter> {
     let exporter_name = cli.exporter.clone().unwrap_or("csv".to_string());
 
     if exporter_name.eq("csv") {
-        return Box::new(get_csv_exporter(cli, table_info));
+        return Box::new(get_csv_exporter(cli, table_info, write_path));
     }
 
     panic!("exporter {} not exists.", exporter_name);
 }
 
-fn get_csv_exporter(cli : &Cli, table_info : &TableInfo) -> CsvExporter {
-    let write_path = cli.write_path.clone().unwrap();
+fn get_csv_exporter(cli : &Cli, table_info : &TableInfo, write_path : &str) -> CsvExporter {
     let file_size_mb = cli.file_size;
     let is_gzip = cli.gzip;
 
-    return CsvExporter::new(table_info.clone(), &write_path, file_size_mb, is_gzip);
+    return CsvExporter::new(table_info.clone(), write_path, file_size_mb, is_gzip);
 }
\ No newline at end of file
@@ -0,0 +1,345 @@
+use crate::{errors::Error, kviter::{KvIterator, KvPair, MergedKvIterator}};
+use txn_types::{WriteRef, WriteType, TimeStamp, Key};
+
+//looks up the value of cf default by the key, for the values not returned by the iterator of cf default.
+pub type DefaultValueFallback<'a> = Box<dyn Fn(&[u8]) -> Result<Option<Box<[u8]>>, Error> + 'a>;
+
+//a visible version of a key, the latest one unless all the versions are required.
+pub struct MvccValue {
+    //the raw key, decoded from the memcomparable format and without the 'z' prefix and the keyspace prefix.
+    pub key : Box<[u8]>,
+    pub start_ts : TimeStamp,
+    pub commit_ts : TimeStamp,
+    pub value : Box<[u8]>,
+    //the cf in which the value is stored, the short values are in cf write.
+    pub cf : &'static str,
+    //indexes of the write cf iterators in which the write record of this version is found.
+    pub sources : Vec<usize>,
+}
+
+//the raw record in rocksdb on which an error is raised, for the records skipped.
+pub struct ErrorRecord {
+    pub cf : &'static str,
+    pub key : Box<[u8]>,
+    pub value : Box<[u8]>,
+}
+
+//resolves the records of cf write and cf default in the same key range to the latest
+//visible version of every key, the deleted keys are skipped.
+//the long values are stored in cf default with the key (user key, start_ts), which is
+//pointed by the start_ts of the put record in cf write.
+pub struct MvccIterator<'a> {
+    default_cf_iter : KvIterator<'a>,
+    default_cf_buf : Option<KvPair>,
+    default_cf_returned_eof : bool,
+
+    write_cf_iter : MergedKvIterator<'a>,
+    write_cf_buf : Option<(KvPair, Vec<usize>)>,
+
+    //versions committed after it are invisible.
+    read_ts : TimeStamp,
+    //yields every visible put version of the keys instead of the latest one.
+    all_versions : bool,
+
+    //the older versions of the key on which an error is raised are skipped.
+    skip_user_key : Option<Box<[u8]>>,
+    last_error_record : Option<ErrorRecord>,
+
+    //stripped from the raw keys.
+    keyspace_prefix : Vec<u8>,
+
+    //such as the values only in the memtable of titan.
+    default_value_fallback : Option<DefaultValueFallback<'a>>,
+}
+
+impl <'a> MvccIterator<'a> {
+    pub fn new(default_cf_iter : KvIterator<'a>, write_cf_iter : MergedKvIterator<'a>, read_ts : TimeStamp) -> MvccIterator<'a> {
+        return MvccIterator {
+            default_cf_iter,
+            default_cf_buf : None,
+            default_cf_returned_eof : false,
+
+            write_cf_iter,
+            write_cf_buf : None,
+
+            read_ts,
+            all_versions : false,
+
+            skip_user_key : None,
+            last_error_record : None,
+
+            keyspace_prefix : vec![],
+
+            default_value_fallback : None,
+        };
+    }
+
+    pub fn set_keyspace_prefix(&mut self, keyspace_prefix : &[u8]) {
+        self.keyspace_prefix = keyspace_prefix.to_vec();
+    }
+
+    pub fn set_default_value_fallback(&mut self, fallback : DefaultValueFallback<'a>) {
+        self.default_value_fallback = Some(fallback);
+    }
+
+    pub fn set_all_versions(&mut self, all_versions : bool) {
+        self.all_versions = all_versions;
+    }
+
+    //the record on which the last error is raised, None if the error is not raised on a record.
+    pub fn take_last_error_record(&mut self) -> Option<ErrorRecord> {
+        return self.last_error_record.take();
+    }
+
+    fn record_error(&mut self, cf : &'static str, key : &[u8], value : &[u8], err : Error) -> Error {
+        self.last_error_record = Some(ErrorRecord {
+            cf,
+            key : Box::from(key),
+            value : Box::from(value),
+        });
+        return err;
+    }
+
+    fn next_write(&mut self) -> Option<Result<(KvPair, Vec<usize>), Error>> {
+        loop {
+            let kv = match self.write_cf_buf.take() {
+                Some(kv) => kv,
+                None => match self.write_cf_iter.next()? {
+                    Ok(kv) => (kv, self.write_cf_iter.get_last_sources().to_vec()),
+                    Err(e) => return Some(Err(e)),
+                },
+            };
+
+            if let Some(skip_user_key) = self.skip_user_key.as_ref() {
+                let is_skipped = match Key::split_on_ts_for((kv.0).0.as_ref()) {
+                    Ok((user_key, _)) => user_key == skip_user_key.as_ref(),
+                    Err(_) => false,
+                };
+                if is_skipped {
+                    continue;
+                }
+                self.skip_user_key = None;
+            }
+            return Some(Ok(kv));
+        }
+    }
+
+    fn next_default(&mut self) -> Option<Result<KvPair, Error>> {
+        if let Some(kv) = self.default_cf_buf.take() {
+            return Some(Ok(kv));
+        }
+        if self.default_cf_returned_eof {
+            return None;
+        }
+        let res = self.default_cf_iter.next();
+        if res.is_none() {
+            self.default_cf_returned_eof = true;
+        }
+        return res;
+    }
+
+    //find the value of (user_key, start_ts) in cf default, both of the iterators are in
+    //ascending order of the user key, so the entries of the smaller keys are skipped.
+    fn get_default_value(&mut self, user_key : &[u8], start_ts : TimeStamp) -> Result<Option<Box<[u8]>>, Error> {
+        loop {
+            let (key_data, val_data) = match self.next_default() {
+                None => return Ok(None),
+                Some(res) => res?,
+            };
+
+            let (default_user_key, default_start_ts) = match Key::split_on_ts_for(key_data.as_ref()) {
+                Ok(r) => r,
+                Err(_) => {
+                    let err = Error::CorruptedDataBytes("cf default key decode ts error.".to_string(), key_data.clone());
+                    return Err(self.record_error("default", &key_data, &val_data, err));
+                },
+            };
+
+            if default_user_key < user_key {
+                continue;
+            }
+            if default_user_key > user_key {
+                self.default_cf_buf = Some((key_data, val_data));
+                return Ok(None);
+            }
+
+            //the versions of the same key are in descending order of start_ts.
+            if default_start_ts > start_ts {
+                continue;
+            }
+            if default_start_ts == start_ts {
+                return Ok(Some(val_data));
+            }
+            self.default_cf_buf = Some((key_data, val_data));
+            return Ok(None);
+        }
+    }
+
+    fn get_next_value(&mut self) -> Option<Result<MvccValue, Error>> {
+        loop {
+            let ((key_data, val_data), sources) = match self.next_write()? {
+                Ok(kv) => kv,
+                Err(e) => return Some(Err(e)),
+            };
+            let (user_key, commit_ts) = match Key::split_on_ts_for(key_data.as_ref()) {
+                Ok(r) => r,
+                Err(_) => {
+                    let err = Error::CorruptedDataBytes("cf write key decode ts error.".to_string(), key_data.clone());
+                    return Some(Err(self.record_error("write", &key_data, &val_data, err)));
+                },
+            };
+            let user_key : Box<[u8]> = Box::from(user_key);
+
+            //the versions of the same key are in descending order of commit_ts,
+            //the first put or delete visible at the read ts is the latest one.
+            //(commit_ts, write_type, start_ts, short_value, sources)
+            let mut latest : Option<(TimeStamp, WriteType, TimeStamp, Option<Box<[u8]>>, Vec<usize>)> = None;
+            let mut cur_version = Some((commit_ts, val_data, sources));
+            while let Some((cur_commit_ts, cur_val_data, cur_sources)) = cur_version.take() {
+                if latest.is_none() && cur_commit_ts <= self.read_ts {
+                    let write_ref = match WriteRef::parse(cur_val_data.as_ref()) {
+                        Ok(r) => r,
+                        Err(_) => {
+                            self.skip_user_key = Some(user_key.clone());
+                            let cur_key_data = Key::from_encoded_slice(&user_key).append_ts(cur_commit_ts).into_encoded();
+                            let err = Error::CorruptedDataBytes("parse WriteRef error.".to_string(), cur_val_data.clone());
+                            return Some(Err(self.record_error("write", &cur_key_data, &cur_val_data, err)));
+                        },
+                    };
+                    match write_ref.write_type {
+                        WriteType::Put | WriteType::Delete => {
+                            latest = Some((cur_commit_ts, write_ref.write_type, write_ref.start_ts, write_ref.short_value.map(Box::from), cur_sources));
+                        },
+                        WriteType::Lock | WriteType::Rollback => (),
+                    }
+                }
+
+                //the older versions of the same key.
+                match self.next_write() {
+                    None => break,
+                    Some(Err(e)) => return Some(Err(e)),
+                    Some(Ok(((next_key_data, next_val_data), next_sources))) => {
+                        let (next_user_key, next_commit_ts) = match Key::split_on_ts_for(next_key_data.as_ref()) {
+                            Ok(r) => r,
+                            Err(_) => {
+                                self.skip_user_key = Some(user_key.clone());
+                                let err = Error::CorruptedDataBytes("cf write key decode ts error.".to_string(), next_key_data.clone());
+                                return Some(Err(self.record_error("write", &next_key_data, &next_val_data, err)));
+                            },
+                        };
+                        if next_user_key == user_key.as_ref() {
+                            cur_version = Some((next_commit_ts, next_val_data, next_sources));
+                        } else {
+                            self.write_cf_buf = Some(((next_key_data, next_val_data), next_sources));
+                        }
+                    },
+                }
+            }
+
+            let (commit_ts, start_ts, short_value, sources) = match latest {
+                Some((commit_ts, WriteType::Put, start_ts, short_value, sources)) => (commit_ts, start_ts, short_value, sources),
+                //deleted or no visible version.
+                _ => continue,
+            };
+
+            return Some(self.make_value(user_key, start_ts, commit_ts, short_value, sources));
+        }
+    }
+
+    //every put version visible at the read ts, in the order of cf write, which is the
+    //ascending order of the keys and the descending order of commit_ts for the same key.
+    fn get_next_version(&mut self) -> Option<Result<MvccValue, Error>> {
+        loop {
+            let ((key_data, val_data), sources) = match self.next_write()? {
+                Ok(kv) => kv,
+                Err(e) => return Some(Err(e)),
+            };
+            let (user_key, commit_ts) = match Key::split_on_ts_for(key_data.as_ref()) {
+                Ok(r) => r,
+                Err(_) => {
+                    let err = Error::CorruptedDataBytes("cf write key decode ts error.".to_string(), key_data.clone());
+                    return Some(Err(self.record_error("write", &key_data, &val_data, err)));
+                },
+            };
+            if commit_ts > self.read_ts {
+                continue;
+            }
+
+            let write_ref = match WriteRef::parse(val_data.as_ref()) {
+                Ok(r) => r,
+                Err(_) => {
+                    let err = Error::CorruptedDataBytes("parse WriteRef error.".to_string(), val_data.clone());
+                    return Some(Err(self.record_error("write", &key_data, &val_data, err)));
+                },
+            };
+            if write_ref.write_type != WriteType::Put {
+                continue;
+            }
+
+            let start_ts = write_ref.start_ts;
+            let short_value = write_ref.short_value.map(Box::from);
+            return Some(self.make_value(Box::from(user_key), start_ts, commit_ts, short_value, sources));
+        }
+    }
+
+    //the value is the short value in the write record, or the one in cf default.
+    fn make_value(&mut self, user_key : Box<[u8]>, start_ts : TimeStamp, commit_ts : TimeStamp, short_value : Option<Box<[u8]>>, sources : Vec<usize>) -> Result<MvccValue, Error> {
+        let (value, cf) = match short_value {
+            Some(v) => (v, "write"),
+            None => match self.get_default_value(user_key.as_ref(), start_ts)? {
+                Some(v) => (v, "default"),
+                None => {
+                    let default_key_data = Key::from_encoded_slice(&user_key).append_ts(start_ts).into_encoded();
+                    //the value may be out of the iterator, such as the one only in the memtable of titan.
+                    let fallback_res = match self.default_value_fallback.as_ref() {
+                        Some(fallback) => fallback(&default_key_data),
+                        None => Ok(None),
+                    };
+                    match fallback_res {
+                        Ok(Some(v)) => (v, "default"),
+                        Ok(None) => {
+                            let err = Error::DanglingWrite(
+                                format!("no value in cf default for the write record, start_ts: {}, commit_ts: {}.", start_ts, commit_ts),
+                                user_key
+                            );
+                            return Err(self.record_error("default", &default_key_data, &[], err));
+                        },
+                        Err(e) => return Err(self.record_error("default", &default_key_data, &[], e)),
+                    }
+                },
+            },
+        };
+
+        let mut encoded_key_ref = user_key.get(1..).unwrap_or_default();
+        let key = match tikv_util::codec::bytes::decode_bytes(&mut encoded_key_ref, false) {
+            Ok(k) => match k.strip_prefix(self.keyspace_prefix.as_slice()) {
+                Some(stripped) => Box::from(stripped),
+                None => k.into_boxed_slice(),
+            },
+            Err(_) => {
+                let err = Error::CorruptedDataBytes("key data decode error.".to_string(), user_key.clone());
+                return Err(self.record_error(cf, &user_key, &value, err));
+            },
+        };
+
+        return Ok(MvccValue {
+            key,
+            start_ts,
+            commit_ts,
+            value,
+            cf,
+            sources,
+        });
+    }
+}
+
+impl <'a> Iterator for MvccIterator<'a> {
+    type Item = Result<MvccValue, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.all_versions {
+            return self.get_next_version();
+        }
+        return self.get_next_value();
+    }
+}
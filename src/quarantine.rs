@@ -0,0 +1,69 @@
+use std::{collections::BTreeMap, path::PathBuf, sync::Mutex};
+
+use crate::{errors::{self, Error}, mvcc::ErrorRecord, report::ReportWriter};
+
+//the records skipped by errors, shared by the reading thread and the exporting threads.
+pub struct Quarantine {
+    inner : Mutex<QuarantineInner>,
+}
+
+struct QuarantineInner {
+    report : ReportWriter,
+    //error kind -> number of the records skipped.
+    error_nums : BTreeMap<&'static str, usize>,
+}
+
+impl Quarantine {
+    pub fn new(write_path : &str) -> Result<Quarantine, Error> {
+        let report = ReportWriter::new(write_path, "quarantine", &["cf", "key", "value", "error"])?;
+        return Ok(Quarantine {
+            inner : Mutex::new(QuarantineInner {
+                report,
+                error_nums : BTreeMap::new(),
+            }),
+        });
+    }
+
+    //the key and value are in hex, empty if the error is not raised on a record.
+    pub fn add(&self, record : Option<&ErrorRecord>, err : &Error) -> Result<(), Error> {
+        let fields = match record {
+            Some(r) => vec![r.cf.to_string(), hex::encode_upper(&r.key), hex::encode_upper(&r.value), err.to_string()],
+            None => match err {
+                //raised by the underlying iterators, with the key only.
+                Error::MissingBlobFile(_, key) | Error::DanglingWrite(_, key) => vec![String::new(), hex::encode_upper(key), String::new(), err.to_string()],
+                _ => vec![String::new(), String::new(), String::new(), err.to_string()],
+            },
+        };
+
+        let mut inner = match self.inner.lock() {
+            Ok(i) => i,
+            Err(_) => return Err(Error::Other("the quarantine is poisoned.".to_string())),
+        };
+        inner.report.write_record(&fields)?;
+        *inner.error_nums.entry(errors::get_error_kind(err)).or_insert(0) += 1;
+        return Ok(());
+    }
+
+    pub fn flush(&self) -> Result<(), Error> {
+        return match self.inner.lock() {
+            Ok(mut i) => i.report.flush(),
+            Err(_) => Err(Error::Other("the quarantine is poisoned.".to_string())),
+        };
+    }
+
+    pub fn get_path(&self) -> PathBuf {
+        return match self.inner.lock() {
+            Ok(i) => i.report.get_path().to_path_buf(),
+            Err(e) => e.into_inner().report.get_path().to_path_buf(),
+        };
+    }
+
+    //(error kind, number of the records skipped)
+    pub fn get_error_nums(&self) -> Vec<(&'static str, usize)> {
+        let inner = match self.inner.lock() {
+            Ok(i) => i,
+            Err(e) => e.into_inner(),
+        };
+        return inner.error_nums.iter().map(|(k, n)| (*k, *n)).collect();
+    }
+}
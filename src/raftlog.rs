@@ -0,0 +1,234 @@
+use std::{collections::{BTreeMap, HashMap}, path::Path};
+
+use kvproto::raft_cmdpb::{CmdType, RaftCmdRequest};
+use kvproto::raft_serverpb::{RaftApplyState, RaftLocalState, RegionLocalState};
+use protobuf::Message;
+use raft::eraftpb::{Entry, EntryType};
+use raft_engine::{Config as RaftEngineConfig, Engine as RaftEngine, MessageExt};
+use rocksdb::{DB, Options};
+
+use crate::{errors::Error, region::RegionInfo};
+
+//the keys of the raft groups in raft-engine, the same as TiKV.
+const RAFT_ENGINE_STATE_KEY : &[u8] = b"R";
+//suffixed with the apply index, only written by the partitioned raft kv.
+const RAFT_ENGINE_REGION_STATE_KEY : &[u8] = &[0x03];
+const RAFT_ENGINE_APPLY_STATE_KEY : &[u8] = &[0x04];
+
+//the changes of the keys in each cf, None for the deleted key.
+pub type RaftChanges = HashMap<String, BTreeMap<Vec<u8>, Option<Box<[u8]>>>>;
+
+//where the raft logs of a store are read from.
+pub enum RaftLogStore {
+    //the rocksdb in the raft directory beside the db directory.
+    RaftDb(DB),
+    //the raft-engine directory used since TiKV 6.x, the entries and the states are in its log files.
+    RaftEngine(RaftEngine),
+}
+
+struct EntryExt;
+
+impl MessageExt for EntryExt {
+    type Entry = Entry;
+
+    fn index(e : &Entry) -> u64 {
+        return e.get_index();
+    }
+}
+
+//raft-engine keeps the logs in the *.raftlog files, and the rewritten ones in the *.rewrite files.
+pub fn is_raft_engine_dir(dir : &Path) -> bool {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(e) => e,
+        Err(_) => return false,
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name.ends_with(".raftlog") || name.ends_with(".rewrite") {
+            return true;
+        }
+    }
+    return false;
+}
+
+impl RaftLogStore {
+    pub fn open(raft_path : &Path) -> Result<RaftLogStore, Error> {
+        let opts = Options::default();
+        return match DB::open_for_read_only(&opts, raft_path, false) {
+            Ok(db) => Ok(RaftLogStore::RaftDb(db)),
+            Err(e) => Err(Error::StorageNodeError(format!("{}: {}", raft_path.display(), e.into_string()))),
+        };
+    }
+
+    //raft-engine can't be opened read only, the files are written when recovering,
+    //so it must be a copy of the raft-engine directory.
+    pub fn open_raft_engine(copy_path : &Path) -> Result<RaftLogStore, Error> {
+        let cfg = RaftEngineConfig {
+            dir : copy_path.to_string_lossy().to_string(),
+            ..Default::default()
+        };
+        return match RaftEngine::open(cfg) {
+            Ok(engine) => Ok(RaftLogStore::RaftEngine(engine)),
+            Err(e) => Err(Error::StorageNodeError(format!("{}: {}", copy_path.display(), e.to_string()))),
+        };
+    }
+
+    fn get_raft_db_value(db : &DB, key : &[u8]) -> Result<Option<Vec<u8>>, Error> {
+        return match db.get(key) {
+            Ok(v) => Ok(v),
+            Err(e) => Err(Error::StorageNodeError(e.into_string())),
+        };
+    }
+
+    //the index of the last committed entry of the region.
+    pub fn get_commit_index(&self, region_id : u64) -> Result<Option<u64>, Error> {
+        let state = match self {
+            RaftLogStore::RaftDb(db) => {
+                let state_data = match Self::get_raft_db_value(db, &keys::raft_state_key(region_id))? {
+                    Some(d) => d,
+                    None => return Ok(None),
+                };
+                let mut state = RaftLocalState::default();
+                if state.merge_from_bytes(&state_data).is_err() {
+                    return Err(Error::CorruptedDataBytes("parse RaftLocalState error.".to_string(), state_data.into_boxed_slice()));
+                }
+                state
+            },
+            RaftLogStore::RaftEngine(engine) => match engine.get_message::<RaftLocalState>(region_id, RAFT_ENGINE_STATE_KEY) {
+                Ok(Some(s)) => s,
+                Ok(None) => return Ok(None),
+                Err(e) => return Err(Error::StorageNodeError(format!("read the raft state of region {} error, {}", region_id, e.to_string()))),
+            },
+        };
+        return Ok(Some(state.get_hard_state().get_commit()));
+    }
+
+    pub fn get_entry(&self, region_id : u64, index : u64) -> Result<Option<Entry>, Error> {
+        return match self {
+            RaftLogStore::RaftDb(db) => {
+                let entry_data = match Self::get_raft_db_value(db, &keys::raft_log_key(region_id, index))? {
+                    Some(d) => d,
+                    None => return Ok(None),
+                };
+                let mut entry = Entry::default();
+                if entry.merge_from_bytes(&entry_data).is_err() {
+                    return Err(Error::CorruptedDataBytes("parse raft log entry error.".to_string(), entry_data.into_boxed_slice()));
+                }
+                Ok(Some(entry))
+            },
+            RaftLogStore::RaftEngine(engine) => match engine.get_entry::<EntryExt>(region_id, index) {
+                Ok(e) => Ok(e),
+                Err(e) => Err(Error::StorageNodeError(format!("read the entry {} of region {} error, {}", index, region_id, e.to_string()))),
+            },
+        };
+    }
+
+    //the region states in raft-engine, with the latest region state and apply state of each raft group.
+    //they are only there for the partitioned raft kv, the others keep them in the raft cf of the kv db.
+    pub fn get_regions(&self) -> Result<Vec<RegionInfo>, Error> {
+        let engine = match self {
+            RaftLogStore::RaftDb(_) => return Ok(vec![]),
+            RaftLogStore::RaftEngine(engine) => engine,
+        };
+
+        let mut ret = vec![];
+        for region_id in engine.raft_groups() {
+            let local_state = match Self::get_latest_message::<RegionLocalState>(engine, region_id, RAFT_ENGINE_REGION_STATE_KEY, RAFT_ENGINE_APPLY_STATE_KEY)? {
+                Some(s) => s,
+                None => continue,
+            };
+            let apply_state = Self::get_latest_message::<RaftApplyState>(engine, region_id, RAFT_ENGINE_APPLY_STATE_KEY, &[RAFT_ENGINE_APPLY_STATE_KEY[0] + 1])?;
+            ret.push(RegionInfo { local_state, apply_state });
+        }
+        return Ok(ret);
+    }
+
+    //the message with the largest key in [start_key, end_key) of the raft group.
+    fn get_latest_message<M : Message>(engine : &RaftEngine, region_id : u64, start_key : &[u8], end_key : &[u8]) -> Result<Option<M>, Error> {
+        let mut ret = None;
+        let scan_res = engine.scan_messages::<M, _>(region_id, Some(start_key), Some(end_key), true, |_, msg| {
+            ret = Some(msg);
+            return false;
+        });
+        if let Err(e) = scan_res {
+            return Err(Error::StorageNodeError(format!("read the states of region {} error, {}", region_id, e.to_string())));
+        }
+        return Ok(ret);
+    }
+}
+
+#[derive(Default)]
+pub struct RaftReplaySummary {
+    //the regions which have the committed entries not applied.
+    pub region_num : usize,
+    pub entry_num : usize,
+    pub put_num : usize,
+    pub delete_num : usize,
+    //the admin commands, the unsupported commands and the entries which can't be parsed.
+    pub skipped_num : usize,
+    //the committed entries which are not found in the raft log.
+    pub missing_entry_num : u64,
+}
+
+//the puts and deletes of the committed entries in (applied_index, commit_index] of the region.
+pub fn replay_region(raft_log : &RaftLogStore, region_id : u64, applied_index : u64, changes : &mut RaftChanges, summary : &mut RaftReplaySummary) -> Result<(), Error> {
+    let commit_index = match raft_log.get_commit_index(region_id)? {
+        Some(i) => i,
+        None => return Ok(()),
+    };
+    if commit_index <= applied_index {
+        return Ok(());
+    }
+
+    summary.region_num += 1;
+    for index in applied_index + 1..=commit_index {
+        let entry = match raft_log.get_entry(region_id, index)? {
+            Some(e) => e,
+            None => {
+                summary.missing_entry_num += commit_index - index + 1;
+                break;
+            },
+        };
+        summary.entry_num += 1;
+        apply_entry(&entry, changes, summary);
+    }
+    return Ok(());
+}
+
+fn apply_entry(entry : &Entry, changes : &mut RaftChanges, summary : &mut RaftReplaySummary) {
+    //the empty entries are proposed by the new leaders.
+    if entry.get_entry_type() != EntryType::EntryNormal || entry.get_data().is_empty() {
+        return;
+    }
+
+    let mut cmd = RaftCmdRequest::default();
+    if cmd.merge_from_bytes(entry.get_data()).is_err() || cmd.has_admin_request() {
+        summary.skipped_num += 1;
+        return;
+    }
+
+    for req in cmd.get_requests() {
+        match req.get_cmd_type() {
+            CmdType::Put => {
+                let put = req.get_put();
+                changes.entry(get_cf_name(put.get_cf()).to_string()).or_default()
+                    .insert(keys::data_key(put.get_key()), Some(Box::from(put.get_value())));
+                summary.put_num += 1;
+            },
+            CmdType::Delete => {
+                let delete = req.get_delete();
+                changes.entry(get_cf_name(delete.get_cf()).to_string()).or_default()
+                    .insert(keys::data_key(delete.get_key()), None);
+                summary.delete_num += 1;
+            },
+            _ => summary.skipped_num += 1,
+        }
+    }
+}
+
+fn get_cf_name(cf : &str) -> &str {
+    if cf.is_empty() {
+        return "default";
+    }
+    return cf;
+}
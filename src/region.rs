@@ -0,0 +1,67 @@
+use kvproto::raft_serverpb::{PeerState, RaftApplyState, RegionLocalState};
+use protobuf::Message;
+
+use crate::errors::Error;
+
+//the region meta of this store, read from the raft cf.
+#[derive(Clone)]
+pub struct RegionInfo {
+    pub local_state : RegionLocalState,
+    pub apply_state : Option<RaftApplyState>,
+}
+
+impl RegionInfo {
+    pub fn get_id(&self) -> u64 {
+        return self.local_state.get_region().get_id();
+    }
+
+    pub fn get_state(&self) -> PeerState {
+        return self.local_state.get_state();
+    }
+
+    pub fn is_normal(&self) -> bool {
+        return self.get_state() == PeerState::Normal;
+    }
+
+    //the range of the data keys(prefixed with 'z') of the region.
+    pub fn get_data_key_range(&self) -> (Vec<u8>, Vec<u8>) {
+        let region = self.local_state.get_region();
+        return (keys::enc_start_key(region), keys::enc_end_key(region));
+    }
+
+    //if the region overlaps with the data key range [lower_bound, upper_bound).
+    pub fn is_overlapped(&self, lower_bound : &[u8], upper_bound : &[u8]) -> bool {
+        let (start_key, end_key) = self.get_data_key_range();
+        return start_key.as_slice() < upper_bound && lower_bound < end_key.as_slice();
+    }
+}
+
+pub fn parse_region_local_state(data : &[u8]) -> Result<RegionLocalState, Error> {
+    let mut state = RegionLocalState::default();
+    if state.merge_from_bytes(data).is_err() {
+        return Err(Error::CorruptedDataBytes("parse RegionLocalState error.".to_string(), Box::from(data)));
+    }
+    return Ok(state);
+}
+
+pub fn parse_raft_apply_state(data : &[u8]) -> Result<RaftApplyState, Error> {
+    let mut state = RaftApplyState::default();
+    if state.merge_from_bytes(data).is_err() {
+        return Err(Error::CorruptedDataBytes("parse RaftApplyState error.".to_string(), Box::from(data)));
+    }
+    return Ok(state);
+}
+
+//intersects [lower_bound, upper_bound) with the sorted and non-overlapping ranges.
+pub fn intersect_ranges(ranges : &[(Vec<u8>, Vec<u8>)], lower_bound : &[u8], upper_bound : &[u8]) -> Vec<(Vec<u8>, Vec<u8>)> {
+    let mut ret = vec![];
+    for (start_key, end_key) in ranges {
+        if start_key.as_slice() >= upper_bound || end_key.as_slice() <= lower_bound {
+            continue;
+        }
+        let start = std::cmp::max(start_key.as_slice(), lower_bound);
+        let end = std::cmp::min(end_key.as_slice(), upper_bound);
+        ret.push((start.to_vec(), end.to_vec()));
+    }
+    return ret;
+}
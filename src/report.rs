@@ -0,0 +1,63 @@
+use std::{fs::File, path::{Path, PathBuf}};
+
+use crate::errors::Error;
+
+//a csv file beside the exported files, for the records which need to be checked by hand.
+pub struct ReportWriter {
+    path : PathBuf,
+    csv_writer : csv::Writer<File>,
+    record_num : usize,
+}
+
+impl ReportWriter {
+    //the report of ~/user.csv named "replicas" is ~/user.replicas.csv.
+    pub fn new(write_path : &str, report_name : &str, header : &[&str]) -> Result<ReportWriter, Error> {
+        let path = get_report_path(write_path, report_name);
+        let mut csv_writer = match csv::Writer::from_path(&path) {
+            Ok(w) => w,
+            Err(e) => return Err(Error::IO(format!("create report file {} error, {}", path.display(), e.to_string()))),
+        };
+        if let Err(e) = csv_writer.write_record(header) {
+            return Err(Error::IO(format!("write report file {} error, {}", path.display(), e.to_string())));
+        }
+
+        return Ok(ReportWriter {
+            path,
+            csv_writer,
+            record_num : 0,
+        });
+    }
+
+    pub fn write_record(&mut self, record : &[String]) -> Result<(), Error> {
+        if let Err(e) = self.csv_writer.write_record(record) {
+            return Err(Error::IO(format!("write report file {} error, {}", self.path.display(), e.to_string())));
+        }
+        self.record_num += 1;
+        return Ok(());
+    }
+
+    pub fn flush(&mut self) -> Result<(), Error> {
+        if let Err(e) = self.csv_writer.flush() {
+            return Err(Error::IO(format!("write report file {} error, {}", self.path.display(), e.to_string())));
+        }
+        return Ok(());
+    }
+
+    pub fn get_path(&self) -> &Path {
+        return &self.path;
+    }
+
+    pub fn get_record_num(&self) -> usize {
+        return self.record_num;
+    }
+}
+
+fn get_report_path(write_path : &str, report_name : &str) -> PathBuf {
+    let path = Path::new(write_path);
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("export");
+    let file_name = format!("{}.{}.csv", stem, report_name);
+    return match path.parent() {
+        Some(parent) => parent.join(file_name),
+        None => PathBuf::from(file_name),
+    };
+}
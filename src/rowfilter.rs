@@ -0,0 +1,689 @@
+use std::cmp::Ordering;
+
+use crate::{errors::Error, datum::DatumRef, tidbtypes::TableInfo};
+
+//the columns to export and the condition of the rows, shared by the exporting threads.
+pub struct RowSelection {
+    //offsets in table_info.cols, the exported columns first, then the ones only in the condition.
+    col_offsets : Vec<usize>,
+    export_col_num : usize,
+    //the columns in it are the indexes in col_offsets.
+    condition : Option<Expr>,
+}
+
+impl RowSelection {
+    //all the columns are exported if columns is empty.
+    pub fn new(table_info : &TableInfo, columns : &[String], where_str : Option<&str>) -> Result<RowSelection, String> {
+        let mut col_offsets = vec![];
+        if columns.is_empty() {
+            col_offsets.extend(0..table_info.cols.len());
+        } else {
+            for name in columns {
+                col_offsets.push(find_col_offset(table_info, name)?);
+            }
+        }
+        let export_col_num = col_offsets.len();
+
+        let condition = match where_str {
+            Some(w) => {
+                let tokens = tokenize(w)?;
+                let mut parser = Parser { tokens, pos : 0, table_info, col_offsets : &mut col_offsets };
+                let expr = parser.parse_or()?;
+                if parser.pos < parser.tokens.len() {
+                    return Err(format!("unexpected {} in --where.", parser.tokens[parser.pos]));
+                }
+                Some(expr)
+            },
+            None => None,
+        };
+
+        return Ok(RowSelection { col_offsets, export_col_num, condition });
+    }
+
+    //the offsets of the columns to locate in the rows, the exported ones are the first get_export_col_num().
+    pub fn get_col_offsets(&self) -> &[usize] {
+        return &self.col_offsets;
+    }
+
+    pub fn get_export_col_num(&self) -> usize {
+        return self.export_col_num;
+    }
+
+    //the datum refs are of the columns in get_col_offsets(), the unknown results of NULL are not selected.
+    pub fn is_selected(&self, datum_refs : &[DatumRef]) -> Result<bool, Error> {
+        return match self.condition.as_ref() {
+            Some(expr) => Ok(expr.eval(datum_refs)? == Some(true)),
+            None => Ok(true),
+        };
+    }
+}
+
+fn find_col_offset(table_info : &TableInfo, name : &str) -> Result<usize, String> {
+    let name_l = name.trim().to_lowercase();
+    return match table_info.cols.iter().position(|col| col.name.L == name_l) {
+        Some(offset) => Ok(offset),
+        None => Err(format!("column {} not found in table {}.", name.trim(), table_info.name.L)),
+    };
+}
+
+#[derive(Debug, Clone, Copy)]
+enum CmpOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug)]
+enum Literal {
+    Null,
+    //compared as numbers if the value of the column is a number too.
+    Number(String),
+    Str(String),
+}
+
+#[derive(Debug)]
+enum LikeToken {
+    //%
+    Any,
+    //_
+    One,
+    Char(char),
+}
+
+#[derive(Debug)]
+enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Compare(usize, CmpOp, Literal),
+    In(usize, Vec<Literal>),
+    IsNull(usize),
+    Like(usize, Vec<LikeToken>),
+}
+
+impl Expr {
+    //the three-valued logic of SQL, None for unknown.
+    fn eval(&self, datum_refs : &[DatumRef]) -> Result<Option<bool>, Error> {
+        return match self {
+            Expr::And(l, r) => match (l.eval(datum_refs)?, r.eval(datum_refs)?) {
+                (Some(false), _) | (_, Some(false)) => Ok(Some(false)),
+                (Some(true), Some(true)) => Ok(Some(true)),
+                _ => Ok(None),
+            },
+            Expr::Or(l, r) => match (l.eval(datum_refs)?, r.eval(datum_refs)?) {
+                (Some(true), _) | (_, Some(true)) => Ok(Some(true)),
+                (Some(false), Some(false)) => Ok(Some(false)),
+                _ => Ok(None),
+            },
+            Expr::Not(e) => Ok(e.eval(datum_refs)?.map(|b| !b)),
+            Expr::Compare(idx, op, literal) => {
+                let ord = match compare(&datum_refs[*idx], literal)? {
+                    Some(o) => o,
+                    None => return Ok(None),
+                };
+                Ok(Some(match op {
+                    CmpOp::Eq => ord == Ordering::Equal,
+                    CmpOp::Ne => ord != Ordering::Equal,
+                    CmpOp::Lt => ord == Ordering::Less,
+                    CmpOp::Le => ord != Ordering::Greater,
+                    CmpOp::Gt => ord == Ordering::Greater,
+                    CmpOp::Ge => ord != Ordering::Less,
+                }))
+            },
+            Expr::In(idx, literals) => {
+                let mut has_null = false;
+                for literal in literals {
+                    match compare(&datum_refs[*idx], literal)? {
+                        Some(Ordering::Equal) => return Ok(Some(true)),
+                        Some(_) => (),
+                        None => has_null = true,
+                    }
+                }
+                Ok(if has_null { None } else { Some(false) })
+            },
+            Expr::IsNull(idx) => Ok(Some(datum_refs[*idx].is_null())),
+            Expr::Like(idx, pattern) => {
+                let d = &datum_refs[*idx];
+                if d.is_null() {
+                    return Ok(None);
+                }
+                let value : Vec<char> = d.try_to_string()?.chars().collect();
+                Ok(Some(is_like_matched(&value, pattern)))
+            },
+        };
+    }
+}
+
+//None if any of them is NULL, the strings are compared by bytes without the collation of the column.
+fn compare(d : &DatumRef, literal : &Literal) -> Result<Option<Ordering>, Error> {
+    if d.is_null() {
+        return Ok(None);
+    }
+    let value = d.try_to_string()?;
+    return match literal {
+        Literal::Null => Ok(None),
+        Literal::Number(n) => {
+            if let (Ok(v), Ok(l)) = (value.parse::<i128>(), n.parse::<i128>()) {
+                return Ok(Some(v.cmp(&l)));
+            }
+            match (value.parse::<f64>(), n.parse::<f64>()) {
+                (Ok(v), Ok(l)) => Ok(v.partial_cmp(&l)),
+                _ => Ok(Some(value.as_str().cmp(n.as_str()))),
+            }
+        },
+        Literal::Str(s) => Ok(Some(value.as_str().cmp(s.as_str()))),
+    };
+}
+
+//the wildcard matching with backtracking to the last %.
+fn is_like_matched(value : &[char], pattern : &[LikeToken]) -> bool {
+    let (mut v, mut p) = (0, 0);
+    //(position of the last %, position in the value it's matched to)
+    let mut last_any : Option<(usize, usize)> = None;
+    while v < value.len() {
+        match pattern.get(p) {
+            Some(LikeToken::Any) => {
+                last_any = Some((p, v));
+                p += 1;
+                continue;
+            },
+            Some(LikeToken::One) => {
+                v += 1;
+                p += 1;
+                continue;
+            },
+            Some(LikeToken::Char(c)) if *c == value[v] => {
+                v += 1;
+                p += 1;
+                continue;
+            },
+            _ => (),
+        }
+        match last_any {
+            Some((any_p, any_v)) => {
+                last_any = Some((any_p, any_v + 1));
+                p = any_p + 1;
+                v = any_v + 1;
+            },
+            None => return false,
+        }
+    }
+    return pattern[p..].iter().all(|t| matches!(t, LikeToken::Any));
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    //(name, is quoted by `)
+    Ident(String, bool),
+    Number(String),
+    Str(String),
+    Op(&'static str),
+    LParen,
+    RParen,
+    Comma,
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Ident(name, _) => write!(f, "'{}'", name),
+            Token::Number(n) => write!(f, "'{}'", n),
+            Token::Str(s) => write!(f, "'{}'", s),
+            Token::Op(op) => write!(f, "'{}'", op),
+            Token::LParen => write!(f, "'('"),
+            Token::RParen => write!(f, "')'"),
+            Token::Comma => write!(f, "','"),
+        }
+    }
+}
+
+fn tokenize(s : &str) -> Result<Vec<Token>, String> {
+    let chars : Vec<char> = s.chars().collect();
+    let mut tokens = vec![];
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        match c {
+            '(' => tokens.push(Token::LParen),
+            ')' => tokens.push(Token::RParen),
+            ',' => tokens.push(Token::Comma),
+            '=' => tokens.push(Token::Op("=")),
+            '!' | '<' | '>' => {
+                let next = chars.get(i + 1).copied();
+                let op = match (c, next) {
+                    ('!', Some('=')) | ('<', Some('>')) => "!=",
+                    ('<', Some('=')) => "<=",
+                    ('>', Some('=')) => ">=",
+                    ('<', _) => "<",
+                    ('>', _) => ">",
+                    _ => return Err(format!("unexpected '{}' in --where.", c)),
+                };
+                if op.len() == 2 {
+                    i += 1;
+                }
+                tokens.push(Token::Op(op));
+            },
+            '\'' | '"' | '`' => {
+                //the quote is escaped by doubling it or by a backslash.
+                let mut value = String::new();
+                let mut closed = false;
+                i += 1;
+                while i < chars.len() {
+                    if chars[i] == '\\' && c != '`' && i + 1 < chars.len() {
+                        value.push_str(&unescape_char(chars[i + 1]));
+                        i += 2;
+                        continue;
+                    }
+                    if chars[i] == c {
+                        if chars.get(i + 1) == Some(&c) {
+                            value.push(c);
+                            i += 2;
+                            continue;
+                        }
+                        closed = true;
+                        break;
+                    }
+                    value.push(chars[i]);
+                    i += 1;
+                }
+                if !closed {
+                    return Err(format!("unclosed {} in --where.", c));
+                }
+                if c == '`' {
+                    tokens.push(Token::Ident(value, true));
+                } else {
+                    tokens.push(Token::Str(value));
+                }
+            },
+            _ if c.is_ascii_digit() || c == '-' || c == '.' => {
+                let start = i;
+                i += 1;
+                while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '.' || ((chars[i] == '-' || chars[i] == '+') && (chars[i - 1] == 'e' || chars[i - 1] == 'E'))) {
+                    i += 1;
+                }
+                tokens.push(Token::Number(chars[start..i].iter().collect()));
+                continue;
+            },
+            _ if c.is_alphanumeric() || c == '_' || c == '$' => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '$') {
+                    i += 1;
+                }
+                tokens.push(Token::Ident(chars[start..i].iter().collect(), false));
+                continue;
+            },
+            _ => return Err(format!("unexpected '{}' in --where.", c)),
+        }
+        i += 1;
+    }
+    return Ok(tokens);
+}
+
+//the escape sequences of the string literals of mysql, \% and \_ are kept for the LIKE patterns.
+fn unescape_char(c : char) -> String {
+    return match c {
+        '0' => "\0".to_string(),
+        'b' => "\x08".to_string(),
+        'n' => "\n".to_string(),
+        'r' => "\r".to_string(),
+        't' => "\t".to_string(),
+        'Z' => "\x1a".to_string(),
+        '%' | '_' => format!("\\{}", c),
+        _ => c.to_string(),
+    };
+}
+
+//or_expr := and_expr (OR and_expr)*
+//and_expr := not_expr (AND not_expr)*
+//not_expr := NOT not_expr | '(' or_expr ')' | predicate
+//predicate := column (op literal | [NOT] IN (literal, ...) | IS [NOT] NULL | [NOT] LIKE string)
+struct Parser<'a> {
+    tokens : Vec<Token>,
+    pos : usize,
+    table_info : &'a TableInfo,
+    col_offsets : &'a mut Vec<usize>,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        return self.tokens.get(self.pos);
+    }
+
+    fn next(&mut self) -> Result<&Token, String> {
+        self.pos += 1;
+        return match self.tokens.get(self.pos - 1) {
+            Some(t) => Ok(t),
+            None => Err("unexpected end of --where.".to_string()),
+        };
+    }
+
+    //the unquoted keyword, case insensitive.
+    fn is_keyword(&self, keyword : &str) -> bool {
+        return matches!(self.peek(), Some(Token::Ident(name, false)) if name.eq_ignore_ascii_case(keyword));
+    }
+
+    fn expect_keyword(&mut self, keyword : &str) -> Result<(), String> {
+        if !self.is_keyword(keyword) {
+            return Err(format!("{} is expected in --where.", keyword));
+        }
+        self.pos += 1;
+        return Ok(());
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, String> {
+        let mut expr = self.parse_and()?;
+        while self.is_keyword("OR") {
+            self.pos += 1;
+            expr = Expr::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }
+        return Ok(expr);
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, String> {
+        let mut expr = self.parse_not()?;
+        while self.is_keyword("AND") {
+            self.pos += 1;
+            expr = Expr::And(Box::new(expr), Box::new(self.parse_not()?));
+        }
+        return Ok(expr);
+    }
+
+    fn parse_not(&mut self) -> Result<Expr, String> {
+        if self.is_keyword("NOT") {
+            self.pos += 1;
+            return Ok(Expr::Not(Box::new(self.parse_not()?)));
+        }
+        if self.peek() == Some(&Token::LParen) {
+            self.pos += 1;
+            let expr = self.parse_or()?;
+            if self.next()? != &Token::RParen {
+                return Err("')' is expected in --where.".to_string());
+            }
+            return Ok(expr);
+        }
+        return self.parse_predicate();
+    }
+
+    fn parse_predicate(&mut self) -> Result<Expr, String> {
+        let idx = match self.next()? {
+            Token::Ident(name, _) => {
+                let name = name.clone();
+                self.get_col_idx(&name)?
+            },
+            t => return Err(format!("a column is expected in --where, found {}.", t)),
+        };
+
+        if self.is_keyword("IS") {
+            self.pos += 1;
+            let is_not = self.is_keyword("NOT");
+            if is_not {
+                self.pos += 1;
+            }
+            self.expect_keyword("NULL")?;
+            return Ok(Self::negate(Expr::IsNull(idx), is_not));
+        }
+
+        let is_not = self.is_keyword("NOT");
+        if is_not {
+            self.pos += 1;
+        }
+        if self.is_keyword("IN") {
+            self.pos += 1;
+            if self.next()? != &Token::LParen {
+                return Err("'(' is expected after IN in --where.".to_string());
+            }
+            let mut literals = vec![self.parse_literal()?];
+            loop {
+                match self.next()? {
+                    Token::Comma => literals.push(self.parse_literal()?),
+                    Token::RParen => break,
+                    t => return Err(format!("',' or ')' is expected in --where, found {}.", t)),
+                }
+            }
+            return Ok(Self::negate(Expr::In(idx, literals), is_not));
+        }
+        if self.is_keyword("LIKE") {
+            self.pos += 1;
+            let pattern = match self.next()? {
+                Token::Str(s) => parse_like_pattern(s),
+                t => return Err(format!("a string is expected after LIKE in --where, found {}.", t)),
+            };
+            return Ok(Self::negate(Expr::Like(idx, pattern), is_not));
+        }
+        if is_not {
+            return Err("IN or LIKE is expected after NOT in --where.".to_string());
+        }
+
+        let op = match self.next()? {
+            Token::Op("=") => CmpOp::Eq,
+            Token::Op("!=") => CmpOp::Ne,
+            Token::Op("<") => CmpOp::Lt,
+            Token::Op("<=") => CmpOp::Le,
+            Token::Op(">") => CmpOp::Gt,
+            Token::Op(">=") => CmpOp::Ge,
+            t => return Err(format!("an operator is expected in --where, found {}.", t)),
+        };
+        return Ok(Expr::Compare(idx, op, self.parse_literal()?));
+    }
+
+    fn parse_literal(&mut self) -> Result<Literal, String> {
+        return match self.next()? {
+            Token::Number(n) => Ok(Literal::Number(n.clone())),
+            Token::Str(s) => Ok(Literal::Str(s.clone())),
+            Token::Ident(name, false) if name.eq_ignore_ascii_case("NULL") => Ok(Literal::Null),
+            t => Err(format!("a value is expected in --where, found {}.", t)),
+        };
+    }
+
+    fn negate(expr : Expr, is_not : bool) -> Expr {
+        if is_not {
+            return Expr::Not(Box::new(expr));
+        }
+        return expr;
+    }
+
+    //the index in col_offsets, the column is appended if it's not exported.
+    fn get_col_idx(&mut self, name : &str) -> Result<usize, String> {
+        let offset = find_col_offset(self.table_info, name)?;
+        if let Some(idx) = self.col_offsets.iter().position(|o| *o == offset) {
+            return Ok(idx);
+        }
+        self.col_offsets.push(offset);
+        return Ok(self.col_offsets.len() - 1);
+    }
+}
+
+//% and _ are the wildcards, escaped by a backslash.
+fn parse_like_pattern(pattern : &str) -> Vec<LikeToken> {
+    let mut ret = vec![];
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        ret.push(match c {
+            '%' => LikeToken::Any,
+            '_' => LikeToken::One,
+            '\\' => LikeToken::Char(chars.next().unwrap_or('\\')),
+            _ => LikeToken::Char(c),
+        });
+    }
+    return ret;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_table_info() -> TableInfo {
+        let col = |id : i64, name : &str, tp : u8| format!(
+            r#"{{"id":{},"name":{{"O":"{}","L":"{}"}},"offset":{},"type":{{"Tp":{},"Flag":0,"Flen":0,"Decimal":0,"Charset":"utf8mb4","Collate":"utf8mb4_bin","Elems":null}},"state":5,"comment":"","hidden":false,"version":2}}"#,
+            id, name, name.to_lowercase(), id - 1, tp
+        );
+        let json = format!(
+            r#"{{"id":100,"name":{{"O":"t","L":"t"}},"charset":"utf8mb4","collate":"utf8mb4_bin","cols":[{},{},{}],"index_info":null,"state":5,"pk_is_handle":false,"is_common_handle":false,"common_handle_version":0,"comment":"","auto_inc_id":0,"auto_id_cache":0,"update_timestamp":0,"version":5}}"#,
+            col(1, "id", 8), col(2, "Name", 15), col(3, "age", 3)
+        );
+        return serde_json::from_str(&json).unwrap();
+    }
+
+    fn is_selected(table_info : &TableInfo, where_str : &str, id : i64, name : Option<&str>, age : Option<i64>) -> bool {
+        let selection = RowSelection::new(table_info, &[], Some(where_str)).unwrap();
+        let id_data = id.to_le_bytes();
+        let age_data = age.map(|a| a.to_le_bytes());
+        let cols = &table_info.cols;
+        let datum_refs = vec![
+            DatumRef::parse_from(&id_data, &cols[0]),
+            match name {
+                Some(n) => DatumRef::parse_from(n.as_bytes(), &cols[1]),
+                None => DatumRef::get_null(&cols[1]),
+            },
+            match age_data.as_ref() {
+                Some(a) => DatumRef::parse_from(a, &cols[2]),
+                None => DatumRef::get_null(&cols[2]),
+            },
+        ];
+        return selection.is_selected(&datum_refs).unwrap();
+    }
+
+    fn is_like(value : &str, pattern : &str) -> bool {
+        let value : Vec<char> = value.chars().collect();
+        return is_like_matched(&value, &parse_like_pattern(pattern));
+    }
+
+    #[test]
+    fn test_tokenize() {
+        let tokens = tokenize("a>=1 AND `b c`<>'x''y\\n' OR c IN (-1.5e3, NULL) and d like 'a\\%'").unwrap();
+        assert_eq!(tokens, vec![
+            Token::Ident("a".to_string(), false),
+            Token::Op(">="),
+            Token::Number("1".to_string()),
+            Token::Ident("AND".to_string(), false),
+            Token::Ident("b c".to_string(), true),
+            Token::Op("!="),
+            Token::Str("x'y\n".to_string()),
+            Token::Ident("OR".to_string(), false),
+            Token::Ident("c".to_string(), false),
+            Token::Ident("IN".to_string(), false),
+            Token::LParen,
+            Token::Number("-1.5e3".to_string()),
+            Token::Comma,
+            Token::Ident("NULL".to_string(), false),
+            Token::RParen,
+            Token::Ident("and".to_string(), false),
+            Token::Ident("d".to_string(), false),
+            Token::Ident("like".to_string(), false),
+            Token::Str("a\\%".to_string()),
+        ]);
+
+        assert!(tokenize("a = 'abc").is_err());
+        assert!(tokenize("a ! b").is_err());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let table_info = get_table_info();
+        assert!(RowSelection::new(&table_info, &["nope".to_string()], None).is_err());
+        assert!(RowSelection::new(&table_info, &[], Some("nope = 1")).is_err());
+        assert!(RowSelection::new(&table_info, &[], Some("id = ")).is_err());
+        assert!(RowSelection::new(&table_info, &[], Some("id = 1 age = 2")).is_err());
+        assert!(RowSelection::new(&table_info, &[], Some("(id = 1")).is_err());
+        assert!(RowSelection::new(&table_info, &[], Some("id NOT = 1")).is_err());
+    }
+
+    #[test]
+    fn test_column_projection() {
+        let table_info = get_table_info();
+        let columns = vec!["age".to_string(), "ID".to_string()];
+        let selection = RowSelection::new(&table_info, &columns, Some("name = 'x' AND id > 1")).unwrap();
+        //the columns only in the condition are located after the exported ones.
+        assert_eq!(selection.get_col_offsets(), &[2, 0, 1]);
+        assert_eq!(selection.get_export_col_num(), 2);
+    }
+
+    #[test]
+    fn test_precedence() {
+        let t = get_table_info();
+        //AND binds tighter than OR.
+        assert!(is_selected(&t, "id = 1 OR id = 2 AND name = 'x'", 1, Some("y"), None));
+        assert!(!is_selected(&t, "id = 1 OR id = 2 AND name = 'x'", 2, Some("y"), None));
+        assert!(!is_selected(&t, "(id = 1 OR id = 2) AND name = 'x'", 1, Some("y"), None));
+        //NOT binds tighter than AND.
+        assert!(is_selected(&t, "NOT id = 1 AND age > 3", 2, None, Some(5)));
+        assert!(!is_selected(&t, "NOT id = 1 AND age > 3", 1, None, Some(5)));
+        assert!(is_selected(&t, "NOT (id = 1 AND age > 3)", 1, None, Some(2)));
+    }
+
+    #[test]
+    fn test_compare() {
+        let t = get_table_info();
+        //numbers are not compared as strings.
+        assert!(is_selected(&t, "age > 9", 1, None, Some(10)));
+        assert!(is_selected(&t, "age >= 9.5", 1, None, Some(10)));
+        assert!(is_selected(&t, "age <= -1", 1, None, Some(-1)));
+        assert!(is_selected(&t, "age != 3", 1, None, Some(10)));
+        assert!(is_selected(&t, "name < 'b'", 1, Some("abc"), None));
+        //the collation is ignored.
+        assert!(!is_selected(&t, "name = 'ABC'", 1, Some("abc"), None));
+        assert!(is_selected(&t, "id IN (3, 2, 1)", 1, None, None));
+        assert!(is_selected(&t, "id NOT IN (2, 3)", 1, None, None));
+    }
+
+    #[test]
+    fn test_null_logic() {
+        let t = get_table_info();
+        //unknown is not selected, and NOT unknown is still unknown.
+        assert!(!is_selected(&t, "age > 3", 1, None, None));
+        assert!(!is_selected(&t, "NOT age > 3", 1, None, None));
+        assert!(!is_selected(&t, "age = NULL", 1, None, None));
+        assert!(is_selected(&t, "age > 3 OR id = 1", 1, None, None));
+        assert!(!is_selected(&t, "age > 3 OR id = 2", 1, None, None));
+        assert!(!is_selected(&t, "age > 3 AND id = 1", 1, None, None));
+        assert!(is_selected(&t, "NOT (age > 3 AND id = 2)", 1, None, None));
+        assert!(is_selected(&t, "age IS NULL", 1, None, None));
+        assert!(!is_selected(&t, "age IS NOT NULL", 1, None, None));
+        assert!(is_selected(&t, "age IS NOT NULL", 1, None, Some(0)));
+        //IN with NULL is unknown unless the value is found.
+        assert!(is_selected(&t, "id IN (1, NULL)", 1, None, None));
+        assert!(!is_selected(&t, "id IN (2, NULL)", 1, None, None));
+        assert!(!is_selected(&t, "id NOT IN (2, NULL)", 1, None, None));
+        assert!(!is_selected(&t, "name LIKE '%'", 1, None, None));
+        assert!(!is_selected(&t, "name NOT LIKE 'a%'", 1, None, None));
+    }
+
+    #[test]
+    fn test_like() {
+        assert!(is_like("abc", "a%"));
+        assert!(is_like("abc", "%c"));
+        assert!(is_like("abc", "a_c"));
+        assert!(is_like("abc", "%"));
+        assert!(is_like("", "%"));
+        assert!(is_like("", "%%"));
+        assert!(!is_like("", "_"));
+        assert!(!is_like("abc", "a_"));
+        assert!(!is_like("abc", "ab"));
+        assert!(!is_like("abc", "abcd%"));
+        //backtracking to the last %.
+        assert!(is_like("aXbXc", "%b%c"));
+        assert!(is_like("mississippi", "%ss%ss%"));
+        assert!(is_like("mississippi", "m%issip%"));
+        assert!(!is_like("mississippi", "%ss%ss%ss%"));
+        assert!(is_like("aaab", "%a_b"));
+        assert!(!is_like("aaab", "%b_"));
+        //escaped wildcards.
+        assert!(is_like("a%c", "a\\%c"));
+        assert!(!is_like("abc", "a\\%c"));
+        assert!(is_like("a_c", "a\\_c"));
+        assert!(!is_like("abc", "a\\_c"));
+
+        let t = get_table_info();
+        assert!(is_selected(&t, "name LIKE 'a\\%'", 1, Some("a%"), None));
+        assert!(!is_selected(&t, "name LIKE 'a\\%'", 1, Some("ab"), None));
+        assert!(is_selected(&t, "name NOT LIKE 'a\\%'", 1, Some("ab"), None));
+    }
+}
@@ -0,0 +1,153 @@
+use std::collections::HashMap;
+
+use tidb_query_datatype::{FieldTypeTp, FieldTypeFlag};
+
+use crate::tidbtypes::{self, TableInfo, ColumnInfo, IndexInfo, FieldType, SchemaState};
+
+//the changes of the columns and indexes from the old table info to the new one, one line for each.
+pub fn diff_table_info(old : &TableInfo, new : &TableInfo) -> Vec<String> {
+    let mut ret = vec![];
+
+    if old.name.L != new.name.L {
+        ret.push(format!("table renamed: {} -> {}", old.name.O, new.name.O));
+    }
+    if old.state != new.state {
+        ret.push(format!("table state: {} -> {}", format_schema_state(old.state), format_schema_state(new.state)));
+    }
+
+    //columns are matched by id, a column is added with a new id when its type is changed lossy.
+    let old_cols : HashMap<i64, &ColumnInfo> = old.cols.iter().map(|c| (c.id, c)).collect();
+    let new_cols : HashMap<i64, &ColumnInfo> = new.cols.iter().map(|c| (c.id, c)).collect();
+    for col in old.cols.iter() {
+        if !new_cols.contains_key(&col.id) {
+            ret.push(format!("column dropped: {}", format_column(col)));
+        }
+    }
+    for col in new.cols.iter() {
+        match old_cols.get(&col.id) {
+            None => ret.push(format!("column added: {}", format_column(col))),
+            Some(old_col) => {
+                let old_col_str = format_column(old_col);
+                let new_col_str = format_column(col);
+                if old_col_str != new_col_str || old_col.offset != col.offset {
+                    ret.push(format!("column changed: {} -> {}", old_col_str, new_col_str));
+                }
+            },
+        }
+    }
+
+    let old_indexes : HashMap<i64, &IndexInfo> = old.index_info.iter().map(|i| (i.id, i)).collect();
+    let new_indexes : HashMap<i64, &IndexInfo> = new.index_info.iter().map(|i| (i.id, i)).collect();
+    for index in old.index_info.iter() {
+        if !new_indexes.contains_key(&index.id) {
+            ret.push(format!("index dropped: {}", format_index(index)));
+        }
+    }
+    for index in new.index_info.iter() {
+        match old_indexes.get(&index.id) {
+            None => ret.push(format!("index added: {}", format_index(index))),
+            Some(old_index) => {
+                let old_index_str = format_index(old_index);
+                let new_index_str = format_index(index);
+                if old_index_str != new_index_str {
+                    ret.push(format!("index changed: {} -> {}", old_index_str, new_index_str));
+                }
+            },
+        }
+    }
+
+    return ret;
+}
+
+//the columns and indexes of the table info, one line for each.
+pub fn describe_table_info(table_info : &TableInfo) -> Vec<String> {
+    let mut ret = vec![];
+    for col in table_info.cols.iter() {
+        ret.push(format!("column: {}", format_column(col)));
+    }
+    for index in table_info.index_info.iter() {
+        ret.push(format!("index: {}", format_index(index)));
+    }
+    return ret;
+}
+
+//such as `name` varchar(32) NOT NULL, id: 2
+fn format_column(col : &ColumnInfo) -> String {
+    let mut ret = format!("`{}` {}", col.name.O, format_field_type(&col.field_type));
+    if col.state != tidbtypes::StatePublic {
+        ret.push_str(&format!(" ({})", format_schema_state(col.state)));
+    }
+    if col.hidden {
+        ret.push_str(" hidden");
+    }
+    ret.push_str(&format!(", id: {}", col.id));
+    return ret;
+}
+
+fn format_field_type(field_type : &FieldType) -> String {
+    let tp = FieldTypeTp::from_u8(field_type.Tp).unwrap_or(FieldTypeTp::Unspecified);
+    let mut ret = format!("{:?}", tp).to_lowercase();
+
+    if tp == FieldTypeTp::Enum || tp == FieldTypeTp::Set {
+        let elems : Vec<String> = field_type.Elems.iter().map(|e| format!("'{}'", e)).collect();
+        ret.push_str(&format!("({})", elems.join(",")));
+    } else if field_type.Decimal > 0 && tp != FieldTypeTp::Float && tp != FieldTypeTp::Double {
+        ret.push_str(&format!("({},{})", field_type.Flen as i32, field_type.Decimal));
+    } else if field_type.Flen as i32 > 0 {
+        ret.push_str(&format!("({})", field_type.Flen as i32));
+    }
+
+    if field_type.is_unsigned() {
+        ret.push_str(" unsigned");
+    }
+    if field_type.Flag & FieldTypeFlag::NOT_NULL.bits() != 0 {
+        ret.push_str(" NOT NULL");
+    }
+    if !field_type.Collate.is_empty() && tp != FieldTypeTp::Enum && tp != FieldTypeTp::Set && field_type.Collate != "binary" {
+        ret.push_str(&format!(" COLLATE {}", field_type.Collate));
+    }
+    return ret;
+}
+
+//such as UNIQUE KEY `idx_name`(`name`(10)), id: 2
+fn format_index(index : &IndexInfo) -> String {
+    let cols : Vec<String> = index.idx_cols.iter().map(|c| {
+        if c.length > 0 {
+            format!("`{}`({})", c.name.O, c.length)
+        } else {
+            format!("`{}`", c.name.O)
+        }
+    }).collect();
+
+    let kind = if index.is_primary {
+        "PRIMARY KEY"
+    } else if index.is_unique {
+        "UNIQUE KEY"
+    } else {
+        "KEY"
+    };
+
+    let mut ret = format!("{} `{}`({})", kind, index.idx_name.O, cols.join(","));
+    if index.state != tidbtypes::StatePublic {
+        ret.push_str(&format!(" ({})", format_schema_state(index.state)));
+    }
+    if index.is_invisible {
+        ret.push_str(" invisible");
+    }
+    ret.push_str(&format!(", id: {}", index.id));
+    return ret;
+}
+
+pub fn format_schema_state(state : SchemaState) -> &'static str {
+    return match state {
+        tidbtypes::StateNone => "none",
+        tidbtypes::StateDeleteOnly => "delete only",
+        tidbtypes::StateWriteOnly => "write only",
+        tidbtypes::StateWriteReorganization => "write reorganization",
+        tidbtypes::StateDeleteReorganization => "delete reorganization",
+        tidbtypes::StatePublic => "public",
+        tidbtypes::StateReplicaOnly => "replica only",
+        tidbtypes::StateGlobalTxnOnly => "global txn only",
+        _ => "unknown",
+    };
+}
@@ -0,0 +1,271 @@
+use std::io::Read;
+
+use crate::errors::Error;
+
+//compression type(1 byte) + checksum(4 bytes)
+pub const BLOCK_TRAILER_SIZE : usize = 5;
+
+//from rocksdb definition
+pub const NO_COMPRESSION : u8 = 0x0;
+pub const SNAPPY_COMPRESSION : u8 = 0x1;
+pub const ZLIB_COMPRESSION : u8 = 0x2;
+pub const LZ4_COMPRESSION : u8 = 0x4;
+pub const LZ4HC_COMPRESSION : u8 = 0x5;
+pub const ZSTD_COMPRESSION : u8 = 0x7;
+pub const ZSTD_NOT_FINAL_COMPRESSION : u8 = 0x40;
+
+//from rocksdb definition
+pub const CHECKSUM_TYPE_NO_CHECKSUM : u8 = 0x0;
+pub const CHECKSUM_TYPE_CRC32C : u8 = 0x1;
+pub const CHECKSUM_TYPE_XXHASH : u8 = 0x2;
+pub const CHECKSUM_TYPE_XXHASH64 : u8 = 0x3;
+pub const CHECKSUM_TYPE_XXH3 : u8 = 0x4;
+
+const CRC32C_MASK_DELTA : u32 = 0xa282ead8;
+//the compression type is mixed into the xxh3 checksum of the block data by it.
+const XXH3_LAST_BYTE_PRIME : u32 = 0x6b9083d9;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BlockHandle {
+    pub offset : u64,
+    pub size : u64,
+}
+
+impl BlockHandle {
+    pub fn decode_from(data : &mut &[u8]) -> Result<BlockHandle, Error> {
+        let offset = read_varint64(data)?;
+        let size = read_varint64(data)?;
+        return Ok(BlockHandle { offset, size });
+    }
+}
+
+//the checksum in the trailer covers the block data and the compression type, which are data_with_type.
+pub fn verify_block_checksum(checksum_type : u8, data_with_type : &[u8], expected : u32) -> Result<(), Error> {
+    let (data, last_byte) = match data_with_type.split_last() {
+        Some((last_byte, data)) => (data, *last_byte),
+        None => return Err(Error::CorruptedData("empty block to verify the checksum.".to_string())),
+    };
+    let actual = match checksum_type {
+        CHECKSUM_TYPE_NO_CHECKSUM => return Ok(()),
+        //masked crc, the same as the one of leveldb.
+        CHECKSUM_TYPE_CRC32C => crc32c::crc32c(data_with_type).rotate_right(15).wrapping_add(CRC32C_MASK_DELTA),
+        CHECKSUM_TYPE_XXHASH => xxhash_rust::xxh32::xxh32(data_with_type, 0),
+        CHECKSUM_TYPE_XXHASH64 => xxhash_rust::xxh64::xxh64(data_with_type, 0) as u32,
+        CHECKSUM_TYPE_XXH3 => (xxhash_rust::xxh3::xxh3_64(data) as u32) ^ (last_byte as u32).wrapping_mul(XXH3_LAST_BYTE_PRIME),
+        _ => return Err(Error::CorruptedData(format!("unsupported checksum type: {}.", checksum_type))),
+    };
+
+    if actual != expected {
+        return Err(Error::CorruptedData(format!("block checksum mismatched, expected: {:#x}, actual: {:#x}.", expected, actual)));
+    }
+    return Ok(());
+}
+
+pub fn read_varint64(data : &mut &[u8]) -> Result<u64, Error> {
+    let bytes : &[u8] = *data;
+    let mut result : u64 = 0;
+    let mut shift : u32 = 0;
+    for (i, b) in bytes.iter().enumerate() {
+        if shift > 63 {
+            break;
+        }
+        result |= ((b & 0x7f) as u64) << shift;
+        if b & 0x80 == 0 {
+            *data = &bytes[i + 1..];
+            return Ok(result);
+        }
+        shift += 7;
+    }
+
+    return Err(Error::CorruptedDataBytes("decode varint error.".to_string(), Box::from(&bytes[..bytes.len().min(10)])));
+}
+
+pub fn read_varint32(data : &mut &[u8]) -> Result<u32, Error> {
+    let v = read_varint64(data)?;
+    if v > u32::MAX as u64 {
+        return Err(Error::CorruptedData(format!("varint32 overflow: {}.", v)));
+    }
+    return Ok(v as u32);
+}
+
+//zigzag encoded
+pub fn read_varsigned64(data : &mut &[u8]) -> Result<i64, Error> {
+    let v = read_varint64(data)?;
+    return Ok(((v >> 1) as i64) ^ -((v & 1) as i64));
+}
+
+pub fn read_length_prefixed_slice<'a>(data : &mut &'a [u8]) -> Result<&'a [u8], Error> {
+    let len = read_varint32(data)? as usize;
+    let bytes : &'a [u8] = *data;
+    if bytes.len() < len {
+        return Err(Error::CorruptedData("length prefixed slice out of range.".to_string()));
+    }
+    *data = &bytes[len..];
+    return Ok(&bytes[..len]);
+}
+
+pub fn read_fixed32(data : &[u8]) -> Result<u32, Error> {
+    if data.len() < 4 {
+        return Err(Error::CorruptedData("fixed32 out of range.".to_string()));
+    }
+    return Ok(u32::from_le_bytes([data[0], data[1], data[2], data[3]]));
+}
+
+//the compressed data of format version 2 is prefixed with the decompressed size,
+//the legacy one(format version 0 and 1) is prefixed with a 8 bytes header.
+fn split_decompressed_size(data : &[u8], format_version : u32) -> Result<(usize, &[u8]), Error> {
+    if format_version >= 2 {
+        let mut d = data;
+        let size = read_varint32(&mut d)? as usize;
+        return Ok((size, d));
+    }
+
+    if data.len() < 8 {
+        return Err(Error::CorruptedData("legacy compressed block is too short.".to_string()));
+    }
+    return Ok((read_fixed32(data)? as usize, &data[8..]));
+}
+
+pub fn decompress(compression_type : u8, data : &[u8], format_version : u32) -> Result<Vec<u8>, Error> {
+    match compression_type {
+        NO_COMPRESSION => Ok(data.to_vec()),
+        SNAPPY_COMPRESSION => {
+            match snap::raw::Decoder::new().decompress_vec(data) {
+                Ok(d) => Ok(d),
+                Err(e) => Err(Error::CorruptedData(format!("snappy decompress error, {}.", e.to_string()))),
+            }
+        },
+        ZLIB_COMPRESSION => {
+            let (size, compressed) = split_decompressed_size(data, format_version)?;
+            let mut decoder = flate2::bufread::DeflateDecoder::new(compressed);
+            let mut d = Vec::with_capacity(size);
+            match decoder.read_to_end(&mut d) {
+                Ok(_) => Ok(d),
+                Err(e) => Err(Error::CorruptedData(format!("zlib decompress error, {}.", e.to_string()))),
+            }
+        },
+        LZ4_COMPRESSION | LZ4HC_COMPRESSION => {
+            let (size, compressed) = split_decompressed_size(data, format_version)?;
+            match lz4_flex::block::decompress(compressed, size) {
+                Ok(d) => Ok(d),
+                Err(e) => Err(Error::CorruptedData(format!("lz4 decompress error, {}.", e.to_string()))),
+            }
+        },
+        ZSTD_COMPRESSION | ZSTD_NOT_FINAL_COMPRESSION => {
+            let (size, compressed) = split_decompressed_size(data, 2)?;
+            match zstd::bulk::decompress(compressed, size) {
+                Ok(d) => Ok(d),
+                Err(e) => Err(Error::CorruptedData(format!("zstd decompress error, {}.", e.to_string()))),
+            }
+        },
+        _ => Err(Error::CorruptedData(format!("unsupported compression type: {}.", compression_type))),
+    }
+}
+
+//an uncompressed block, keys are prefix compressed and followed by the restart points.
+pub struct Block {
+    data : Vec<u8>,
+    restarts_offset : usize,
+    restarts : Vec<usize>,
+}
+
+impl Block {
+    pub fn new(data : Vec<u8>) -> Result<Block, Error> {
+        if data.len() < 4 {
+            return Err(Error::CorruptedData("block is too short.".to_string()));
+        }
+
+        //the highest bit marks the data block hash index which is placed before the footer.
+        let packed_footer = read_fixed32(&data[data.len() - 4..])?;
+        let num_restarts = (packed_footer & 0x7fff_ffff) as usize;
+        let mut restarts_end = data.len() - 4;
+        if packed_footer >> 31 == 1 {
+            if restarts_end < 2 {
+                return Err(Error::CorruptedData("block hash index is too short.".to_string()));
+            }
+            let num_buckets = u16::from_le_bytes([data[restarts_end - 2], data[restarts_end - 1]]) as usize;
+            restarts_end = match restarts_end.checked_sub(2 + num_buckets) {
+                Some(e) => e,
+                None => return Err(Error::CorruptedData("block hash index out of range.".to_string())),
+            };
+        }
+
+        let restarts_offset = match restarts_end.checked_sub(num_restarts * 4) {
+            Some(o) => o,
+            None => return Err(Error::CorruptedData("block restarts out of range.".to_string())),
+        };
+
+        let mut restarts = Vec::with_capacity(num_restarts);
+        for i in 0..num_restarts {
+            restarts.push(read_fixed32(&data[restarts_offset + i * 4..])? as usize);
+        }
+
+        return Ok(Block { data, restarts_offset, restarts });
+    }
+
+    pub fn entries(&self) -> Result<Vec<(Vec<u8>, Vec<u8>)>, Error> {
+        let mut ret = Vec::new();
+        let mut key : Vec<u8> = Vec::new();
+        let mut data = &self.data[..self.restarts_offset];
+
+        while !data.is_empty() {
+            let shared = read_varint32(&mut data)? as usize;
+            let non_shared = read_varint32(&mut data)? as usize;
+            let value_len = read_varint32(&mut data)? as usize;
+            if shared > key.len() || data.len() < non_shared + value_len {
+                return Err(Error::CorruptedData("block entry out of range.".to_string()));
+            }
+
+            key.truncate(shared);
+            key.extend_from_slice(&data[..non_shared]);
+            ret.push((key.clone(), data[non_shared..non_shared + value_len].to_vec()));
+            data = &data[non_shared + value_len..];
+        }
+
+        return Ok(ret);
+    }
+
+    //the values of index blocks are block handles, only the size delta is stored for
+    //the entries which are not at a restart point if the values are delta encoded.
+    pub fn index_entries(&self, value_delta_encoded : bool) -> Result<Vec<(Vec<u8>, BlockHandle)>, Error> {
+        if !value_delta_encoded {
+            let mut ret = Vec::new();
+            for (key, val) in self.entries()? {
+                let mut val_ref = val.as_slice();
+                ret.push((key, BlockHandle::decode_from(&mut val_ref)?));
+            }
+            return Ok(ret);
+        }
+
+        let mut ret : Vec<(Vec<u8>, BlockHandle)> = Vec::new();
+        let mut key : Vec<u8> = Vec::new();
+        let mut data = &self.data[..self.restarts_offset];
+
+        while !data.is_empty() {
+            let entry_offset = self.restarts_offset - data.len();
+            let shared = read_varint32(&mut data)? as usize;
+            let non_shared = read_varint32(&mut data)? as usize;
+            if shared > key.len() || data.len() < non_shared {
+                return Err(Error::CorruptedData("index block entry out of range.".to_string()));
+            }
+
+            key.truncate(shared);
+            key.extend_from_slice(&data[..non_shared]);
+            data = &data[non_shared..];
+
+            let handle = if self.restarts.binary_search(&entry_offset).is_ok() || ret.is_empty() {
+                BlockHandle::decode_from(&mut data)?
+            } else {
+                let prev = ret.last().unwrap().1;
+                let size = prev.size as i64 + read_varsigned64(&mut data)?;
+                BlockHandle {
+                    offset : prev.offset + prev.size + BLOCK_TRAILER_SIZE as u64,
+                    size : size as u64,
+                }
+            };
+            ret.push((key.clone(), handle));
+        }
+
+        return Ok(ret);
+    }
+}
@@ -0,0 +1,529 @@
+#![allow(dead_code)]
+use std::{fs::File, io::{Read, Seek, SeekFrom}, path::{Path, PathBuf}, collections::{HashMap, BinaryHeap}, cmp::Ordering};
+
+use crate::{errors::Error, kviter::KvPair};
+
+use self::block::{Block, BlockHandle, BLOCK_TRAILER_SIZE, NO_COMPRESSION, CHECKSUM_TYPE_CRC32C, read_fixed32, read_varint64};
+
+pub mod block;
+
+//from rocksdb definition
+pub const VALUE_TYPE_DELETION : u8 = 0x0;
+pub const VALUE_TYPE_VALUE : u8 = 0x1;
+pub const VALUE_TYPE_MERGE : u8 = 0x2;
+pub const VALUE_TYPE_SINGLE_DELETION : u8 = 0x7;
+pub const VALUE_TYPE_RANGE_DELETION : u8 = 0xF;
+pub const VALUE_TYPE_BLOB_INDEX : u8 = 0x11;
+pub const VALUE_TYPE_DELETION_WITH_TIMESTAMP : u8 = 0x14;
+
+const BLOCK_BASED_TABLE_MAGIC_NUMBER : u64 = 0x88e241b785f4cff7;
+const LEGACY_BLOCK_BASED_TABLE_MAGIC_NUMBER : u64 = 0xdb4775248b80fb57;
+//checksum type(1) + block handles with padding(40) + format version(4) + magic number(8)
+const FOOTER_LENGTH : usize = 53;
+//block handles with padding(40) + magic number(8)
+const LEGACY_FOOTER_LENGTH : usize = 48;
+
+const INDEX_TYPE_BINARY_SEARCH : u32 = 0;
+const INDEX_TYPE_HASH_SEARCH : u32 = 1;
+const INDEX_TYPE_TWO_LEVEL_INDEX_SEARCH : u32 = 2;
+
+pub const PROPERTY_COLUMN_FAMILY_NAME : &str = "rocksdb.column.family.name";
+const PROPERTY_INDEX_TYPE : &str = "rocksdb.block.based.table.index.type";
+const PROPERTY_INDEX_KEY_IS_USER_KEY : &str = "rocksdb.index.key.is.user.key";
+const PROPERTY_INDEX_VALUE_IS_DELTA_ENCODED : &str = "rocksdb.index.value.is.delta.encoded";
+
+const META_BLOCK_PROPERTIES : &[u8] = b"rocksdb.properties";
+const META_BLOCK_RANGE_DEL : &[u8] = b"rocksdb.range_del";
+
+pub struct SstEntry {
+    pub user_key : Vec<u8>,
+    pub seq : u64,
+    pub value_type : u8,
+    pub value : Vec<u8>,
+}
+
+//keys in [start, end) with smaller sequence numbers are deleted.
+#[derive(Debug, Clone)]
+pub struct RangeTombstone {
+    pub start : Vec<u8>,
+    pub end : Vec<u8>,
+    pub seq : u64,
+}
+
+//reads the block based table files of rocksdb without opening the db.
+pub struct SstFileReader {
+    path : PathBuf,
+    file : File,
+    file_size : u64,
+    format_version : u32,
+    //of the blocks, the legacy files are always crc32c.
+    checksum_type : u8,
+    properties : HashMap<String, Vec<u8>>,
+    //(user key which is not less than all the keys in the block, data block handle)
+    data_block_handles : Vec<(Vec<u8>, BlockHandle)>,
+    range_tombstones : Vec<RangeTombstone>,
+}
+
+impl SstFileReader {
+    pub fn open(path : &Path) -> Result<SstFileReader, Error> {
+        let mut file = match File::open(path) {
+            Ok(f) => f,
+            Err(e) => return Err(Error::IO(format!("open sst file {} error, {}", path.display(), e.to_string()))),
+        };
+        let file_size = match file.metadata() {
+            Ok(m) => m.len() as usize,
+            Err(e) => return Err(Error::IO(format!("read sst file {} error, {}", path.display(), e.to_string()))),
+        };
+        if file_size < LEGACY_FOOTER_LENGTH {
+            return Err(Error::StorageNodeError(format!("sst file {} is too short.", path.display())));
+        }
+
+        let footer_len = FOOTER_LENGTH.min(file_size);
+        let mut footer = vec![0u8; footer_len];
+        let read_res = file.seek(SeekFrom::Start((file_size - footer_len) as u64))
+            .and_then(|_| file.read_exact(&mut footer));
+        if let Err(e) = read_res {
+            return Err(Error::IO(format!("read sst file {} footer error, {}", path.display(), e.to_string())));
+        }
+
+        let mut magic_bytes = [0u8; 8];
+        magic_bytes.copy_from_slice(&footer[footer_len - 8..]);
+        let magic = u64::from_le_bytes(magic_bytes);
+        let (format_version, checksum_type, mut handles_data) = if magic == BLOCK_BASED_TABLE_MAGIC_NUMBER && footer_len == FOOTER_LENGTH {
+            (read_fixed32(&footer[footer_len - 12..])?, footer[0], &footer[1..footer_len - 12])
+        } else if magic == LEGACY_BLOCK_BASED_TABLE_MAGIC_NUMBER {
+            (0, CHECKSUM_TYPE_CRC32C, &footer[footer_len - LEGACY_FOOTER_LENGTH..footer_len - 8])
+        } else {
+            return Err(Error::StorageNodeError(format!("{} is not a block based table file.", path.display())));
+        };
+
+        let metaindex_handle = BlockHandle::decode_from(&mut handles_data)?;
+        let index_handle = BlockHandle::decode_from(&mut handles_data)?;
+
+        let mut reader = SstFileReader {
+            path : path.to_path_buf(),
+            file,
+            file_size : file_size as u64,
+            format_version,
+            checksum_type,
+            properties : HashMap::new(),
+            data_block_handles : Vec::new(),
+            range_tombstones : Vec::new(),
+        };
+
+        for (name, handle_data) in reader.read_block(metaindex_handle)?.entries()? {
+            let mut handle_data_ref = handle_data.as_slice();
+            let handle = BlockHandle::decode_from(&mut handle_data_ref)?;
+            if name == META_BLOCK_PROPERTIES {
+                for (k, v) in reader.read_block(handle)?.entries()? {
+                    reader.properties.insert(String::from_utf8_lossy(&k).to_string(), v);
+                }
+            } else if name == META_BLOCK_RANGE_DEL {
+                for (k, v) in reader.read_block(handle)?.entries()? {
+                    let (start, seq, _) = split_internal_key(&k)?;
+                    reader.range_tombstones.push(RangeTombstone { start : start.to_vec(), end : v, seq });
+                }
+            }
+        }
+
+        let index_type = match reader.properties.get(PROPERTY_INDEX_TYPE) {
+            Some(v) => read_fixed32(v)?,
+            None => INDEX_TYPE_BINARY_SEARCH,
+        };
+        let index_key_is_user_key = reader.get_property_u64(PROPERTY_INDEX_KEY_IS_USER_KEY).unwrap_or(0) == 1;
+        let index_value_delta_encoded = reader.get_property_u64(PROPERTY_INDEX_VALUE_IS_DELTA_ENCODED).unwrap_or(0) == 1;
+
+        let index_entries = reader.read_block(index_handle)?.index_entries(index_value_delta_encoded)?;
+        let data_block_index_entries = match index_type {
+            INDEX_TYPE_BINARY_SEARCH | INDEX_TYPE_HASH_SEARCH => index_entries,
+            INDEX_TYPE_TWO_LEVEL_INDEX_SEARCH => {
+                //the top level index points to the index partitions.
+                let mut entries = Vec::new();
+                for (_, partition_handle) in index_entries {
+                    entries.append(&mut reader.read_block(partition_handle)?.index_entries(index_value_delta_encoded)?);
+                }
+                entries
+            },
+            _ => return Err(Error::StorageNodeError(format!("unsupported index type {} of sst file {}.", index_type, path.display()))),
+        };
+
+        for (key, handle) in data_block_index_entries {
+            let user_key = if index_key_is_user_key {
+                key
+            } else {
+                split_internal_key(&key)?.0.to_vec()
+            };
+            reader.data_block_handles.push((user_key, handle));
+        }
+
+        return Ok(reader);
+    }
+
+    pub fn get_path(&self) -> &Path {
+        return &self.path;
+    }
+
+    pub fn get_column_family_name(&self) -> Option<String> {
+        return self.properties.get(PROPERTY_COLUMN_FAMILY_NAME).map(|v| String::from_utf8_lossy(v).to_string());
+    }
+
+    //the integer properties are encoded as varint64.
+    pub fn get_property_u64(&self, name : &str) -> Option<u64> {
+        let mut v = self.properties.get(name)?.as_slice();
+        return read_varint64(&mut v).ok();
+    }
+
+    //not less than all the user keys in the file, None for the empty file.
+    pub fn get_largest_key_bound(&self) -> Option<&[u8]> {
+        return self.data_block_handles.last().map(|(k, _)| k.as_slice());
+    }
+
+    pub fn get_range_tombstones(&self) -> &[RangeTombstone] {
+        return &self.range_tombstones;
+    }
+
+    //empty bounds mean unbounded.
+    pub fn into_iter_range(self, lower_bound : &[u8], upper_bound : &[u8]) -> SstIterator {
+        let next_block_idx = self.data_block_handles.partition_point(|(k, _)| k.as_slice() < lower_bound);
+        return SstIterator {
+            reader : self,
+            next_block_idx,
+            cur_entries : Vec::new().into_iter(),
+            lower_bound : lower_bound.to_vec(),
+            upper_bound : upper_bound.to_vec(),
+            is_eof : false,
+        };
+    }
+
+    fn read_block(&mut self, handle : BlockHandle) -> Result<Block, Error> {
+        //the handles of a corrupted file may point to anywhere with any size.
+        let block_end = handle.size.checked_add(BLOCK_TRAILER_SIZE as u64).and_then(|s| s.checked_add(handle.offset));
+        if block_end.map_or(true, |end| end > self.file_size) {
+            return Err(Error::StorageNodeError(format!("block at {} with size {} is out of sst file {}.", handle.offset, handle.size, self.path.display())));
+        }
+
+        let size = handle.size as usize;
+        let mut buf = vec![0u8; size + BLOCK_TRAILER_SIZE];
+        let read_res = self.file.seek(SeekFrom::Start(handle.offset))
+            .and_then(|_| self.file.read_exact(&mut buf));
+        if let Err(e) = read_res {
+            return Err(Error::IO(format!("read block at {} of sst file {} error, {}", handle.offset, self.path.display(), e.to_string())));
+        }
+
+        let checksum = read_fixed32(&buf[size + 1..])?;
+        if let Err(e) = block::verify_block_checksum(self.checksum_type, &buf[..size + 1], checksum) {
+            return Err(Error::StorageNodeError(format!("read block at {} of sst file {} error, {}", handle.offset, self.path.display(), e.to_string())));
+        }
+
+        let compression_type = buf[size];
+        buf.truncate(size);
+        let data = if compression_type == NO_COMPRESSION {
+            buf
+        } else {
+            match block::decompress(compression_type, &buf, self.format_version) {
+                Ok(d) => d,
+                Err(e) => return Err(Error::StorageNodeError(format!("read block at {} of sst file {} error, {}", handle.offset, self.path.display(), e.to_string()))),
+            }
+        };
+
+        return Block::new(data);
+    }
+}
+
+//(user key, sequence number, value type)
+pub fn split_internal_key(key : &[u8]) -> Result<(&[u8], u64, u8), Error> {
+    if key.len() < 8 {
+        return Err(Error::CorruptedDataBytes("internal key is too short.".to_string(), Box::from(key)));
+    }
+
+    let (user_key, footer) = key.split_at(key.len() - 8);
+    let mut footer_bytes = [0u8; 8];
+    footer_bytes.copy_from_slice(footer);
+    let packed = u64::from_le_bytes(footer_bytes);
+    return Ok((user_key, packed >> 8, (packed & 0xff) as u8));
+}
+
+pub struct SstIterator {
+    reader : SstFileReader,
+    next_block_idx : usize,
+    cur_entries : std::vec::IntoIter<(Vec<u8>, Vec<u8>)>,
+    lower_bound : Vec<u8>,
+    upper_bound : Vec<u8>,
+    is_eof : bool,
+}
+
+impl SstIterator {
+    pub fn get_reader(&self) -> &SstFileReader {
+        return &self.reader;
+    }
+}
+
+impl Iterator for SstIterator {
+    type Item = Result<SstEntry, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.is_eof {
+                return None;
+            }
+
+            if let Some((key, value)) = self.cur_entries.next() {
+                let (user_key, seq, value_type) = match split_internal_key(&key) {
+                    Ok(r) => r,
+                    Err(e) => return Some(Err(e)),
+                };
+                if user_key < self.lower_bound.as_slice() {
+                    continue;
+                }
+                if !self.upper_bound.is_empty() && user_key >= self.upper_bound.as_slice() {
+                    self.is_eof = true;
+                    return None;
+                }
+
+                return Some(Ok(SstEntry { user_key : user_key.to_vec(), seq, value_type, value }));
+            }
+
+            if self.next_block_idx >= self.reader.data_block_handles.len() {
+                self.is_eof = true;
+                return None;
+            }
+
+            let handle = self.reader.data_block_handles[self.next_block_idx].1;
+            self.next_block_idx += 1;
+            match self.reader.read_block(handle).and_then(|b| b.entries()) {
+                Ok(entries) => self.cur_entries = entries.into_iter(),
+                Err(e) => {
+                    self.is_eof = true;
+                    return Some(Err(e));
+                },
+            }
+        }
+    }
+}
+
+struct HeapEntry {
+    entry : SstEntry,
+    source : usize,
+}
+
+//BinaryHeap is a max-heap, the entry should be popped first is the greatest:
+//smaller user key, greater sequence number, and then smaller source(newer file).
+impl Ord for HeapEntry {
+    fn cmp(&self, other : &Self) -> Ordering {
+        return other.entry.user_key.cmp(&self.entry.user_key)
+            .then(self.entry.seq.cmp(&other.entry.seq))
+            .then(other.source.cmp(&self.source));
+    }
+}
+
+impl PartialOrd for HeapEntry {
+    fn partial_cmp(&self, other : &Self) -> Option<Ordering> {
+        return Some(self.cmp(other));
+    }
+}
+
+impl PartialEq for HeapEntry {
+    fn eq(&self, other : &Self) -> bool {
+        return self.cmp(other) == Ordering::Equal;
+    }
+}
+
+impl Eq for HeapEntry {}
+
+//merges the sst files of a column family like the iterator of rocksdb,
+//only the newest visible version of every user key is returned.
+pub struct SstMergeIterator {
+    iters : Vec<SstIterator>,
+    heap : BinaryHeap<HeapEntry>,
+    range_tombstones : Vec<RangeTombstone>,
+    last_user_key : Option<Vec<u8>>,
+    is_inited : bool,
+}
+
+impl SstMergeIterator {
+    //iters should be sorted from the newest file to the oldest one.
+    pub fn new(iters : Vec<SstIterator>) -> SstMergeIterator {
+        let range_tombstones = iters.iter()
+            .flat_map(|i| i.get_reader().get_range_tombstones().iter().cloned())
+            .collect();
+        return SstMergeIterator {
+            iters,
+            heap : BinaryHeap::new(),
+            range_tombstones,
+            last_user_key : None,
+            is_inited : false,
+        };
+    }
+
+    fn fill_from(&mut self, source : usize) -> Result<(), Error> {
+        if let Some(res) = self.iters[source].next() {
+            self.heap.push(HeapEntry { entry : res?, source });
+        }
+        return Ok(());
+    }
+
+    fn is_deleted_by_range(&self, entry : &SstEntry) -> bool {
+        return self.range_tombstones.iter().any(|t| {
+            t.seq > entry.seq && t.start.as_slice() <= entry.user_key.as_slice() && entry.user_key.as_slice() < t.end.as_slice()
+        });
+    }
+}
+
+impl Iterator for SstMergeIterator {
+    type Item = Result<SstEntry, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if !self.is_inited {
+            self.is_inited = true;
+            for source in 0..self.iters.len() {
+                if let Err(e) = self.fill_from(source) {
+                    return Some(Err(e));
+                }
+            }
+        }
+
+        loop {
+            let HeapEntry { entry, source } = self.heap.pop()?;
+            if let Err(e) = self.fill_from(source) {
+                return Some(Err(e));
+            }
+
+            //older versions
+            if self.last_user_key.as_deref() == Some(entry.user_key.as_slice()) {
+                continue;
+            }
+            self.last_user_key = Some(entry.user_key.clone());
+
+            match entry.value_type {
+                VALUE_TYPE_DELETION | VALUE_TYPE_SINGLE_DELETION | VALUE_TYPE_DELETION_WITH_TIMESTAMP => continue,
+                VALUE_TYPE_VALUE | VALUE_TYPE_BLOB_INDEX => {
+                    if self.is_deleted_by_range(&entry) {
+                        continue;
+                    }
+                    return Some(Ok(entry));
+                },
+                _ => {
+                    return Some(Err(Error::CorruptedDataBytes(
+                        format!("unsupported value type {} in sst files.", entry.value_type),
+                        entry.user_key.into_boxed_slice()
+                    )));
+                },
+            }
+        }
+    }
+}
+
+//the entries of the merged sst files as (key, value), the blob indexes of titan can't be resolved here.
+pub struct SstKvIterator {
+    inner : SstMergeIterator,
+}
+
+impl SstKvIterator {
+    pub fn new(inner : SstMergeIterator) -> SstKvIterator {
+        return SstKvIterator { inner };
+    }
+}
+
+impl Iterator for SstKvIterator {
+    type Item = Result<KvPair, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let entry = match self.inner.next()? {
+            Ok(e) => e,
+            Err(e) => return Some(Err(e)),
+        };
+
+        if entry.value_type == VALUE_TYPE_BLOB_INDEX {
+            return Some(Err(Error::CorruptedDataBytes("blob index found but the titan directory doesn't exist.".to_string(), entry.user_key.into_boxed_slice())));
+        }
+        return Some(Ok((entry.user_key.into_boxed_slice(), entry.value.into_boxed_slice())));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rocksdb::{BlockBasedOptions, ChecksumType, DBCompressionType, Options, SstFileWriter};
+
+    use super::*;
+
+    const KEY_NUM : usize = 10000;
+
+    fn write_sst_file(path : &Path, compression_type : DBCompressionType, checksum_type : ChecksumType) {
+        let mut bopts = BlockBasedOptions::default();
+        bopts.set_checksum_type(checksum_type);
+        let mut opts = Options::default();
+        opts.set_compression_type(compression_type);
+        opts.set_block_based_table_factory(&bopts);
+
+        let mut writer = SstFileWriter::create(&opts);
+        writer.open(path).unwrap();
+        for i in 0..KEY_NUM {
+            let key = format!("key{:06}", i);
+            if i % 10 == 9 {
+                writer.delete(key.as_bytes()).unwrap();
+            } else {
+                writer.put(key.as_bytes(), format!("value{}", i).as_bytes()).unwrap();
+            }
+        }
+        writer.finish().unwrap();
+    }
+
+    #[test]
+    fn test_read_sst_file_written_by_rocksdb() {
+        let dir = tempfile::tempdir().unwrap();
+        let compression_types = [DBCompressionType::None, DBCompressionType::Snappy, DBCompressionType::Zlib, DBCompressionType::Lz4, DBCompressionType::Zstd];
+        let checksum_types = [ChecksumType::NoChecksum, ChecksumType::CRC32c, ChecksumType::XXHash, ChecksumType::XXHash64, ChecksumType::XXH3];
+        for (i, (compression_type, checksum_type)) in compression_types.into_iter().zip(checksum_types).enumerate() {
+            let path = dir.path().join(format!("{:06}.sst", i));
+            write_sst_file(&path, compression_type, checksum_type);
+
+            let reader = SstFileReader::open(&path).unwrap();
+            assert_eq!(reader.get_largest_key_bound(), Some(format!("key{:06}", KEY_NUM - 1).as_bytes()));
+            let entries : Vec<SstEntry> = reader.into_iter_range(b"", b"").map(|r| r.unwrap()).collect();
+            assert_eq!(entries.len(), KEY_NUM);
+            for (i, entry) in entries.iter().enumerate() {
+                assert_eq!(entry.user_key, format!("key{:06}", i).into_bytes());
+                if i % 10 == 9 {
+                    assert_eq!(entry.value_type, VALUE_TYPE_DELETION);
+                } else {
+                    assert_eq!(entry.value_type, VALUE_TYPE_VALUE);
+                    assert_eq!(entry.value, format!("value{}", i).into_bytes());
+                }
+            }
+
+            let reader = SstFileReader::open(&path).unwrap();
+            let keys : Vec<Vec<u8>> = reader.into_iter_range(b"key001000", b"key002000").map(|r| r.unwrap().user_key).collect();
+            assert_eq!(keys.len(), 1000);
+            assert_eq!(keys[0], b"key001000".to_vec());
+            assert_eq!(keys[999], b"key001999".to_vec());
+        }
+    }
+
+    #[test]
+    fn test_corrupted_sst_block() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("000001.sst");
+        write_sst_file(&path, DBCompressionType::None, ChecksumType::CRC32c);
+
+        //the first data block is at the beginning of the file.
+        let mut data = std::fs::read(&path).unwrap();
+        data[100] ^= 0xff;
+        std::fs::write(&path, &data).unwrap();
+
+        let reader = SstFileReader::open(&path).unwrap();
+        assert!(reader.into_iter_range(b"", b"").any(|r| r.is_err()));
+    }
+
+    #[test]
+    fn test_block_handle_out_of_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("000001.sst");
+        write_sst_file(&path, DBCompressionType::None, ChecksumType::CRC32c);
+
+        let mut reader = SstFileReader::open(&path).unwrap();
+        let handle = BlockHandle { offset : 0, size : u64::MAX - 1 };
+        assert!(reader.read_block(handle).is_err());
+        let handle = BlockHandle { offset : reader.file_size, size : 1 };
+        assert!(reader.read_block(handle).is_err());
+    }
+}
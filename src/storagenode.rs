@@ -1,15 +1,157 @@
-use std::path::PathBuf;
-use crate::{errors::Error, tidbtypes::{DBInfo, TableInfo}, tabledataiterator::TableDataIterator};
-use std::collections::HashMap;
-use rocksdb::{DB, Options, DBIterator, BlockBasedOptions};
-use txn_types::{WriteRef, Key, WriteType};
-pub struct RocksDbStorageNode {
-    db : DB
+use std::{ops::Bound, path::{Path, PathBuf}};
+use crate::{errors::Error, datum, tidbtypes::{self, DBInfo, TableInfo, IndexInfo, DDLJob, DroppedTableInfo, TableInfoVersion}, tabledataiterator::TableDataIterator, indexdataiterator::IndexDataIterator, kviter::{KvIterator, MergedKvIterator, OverlayKvIterator}};
+use crate::backup::{self, BackupDataFile, BackupSchemas};
+use crate::lockresolver::LockResolver;
+use crate::raftlog::{self, RaftChanges, RaftLogStore, RaftReplaySummary};
+use crate::mvcc::MvccIterator;
+use crate::region::{self, RegionInfo};
+use crate::sst::{SstFileReader, SstMergeIterator, SstKvIterator};
+use crate::titan::{self, TitanBlobReader, TitanKvIterator};
+use std::collections::{HashMap, HashSet};
+use rocksdb::{DB, Options, BlockBasedOptions};
+use txn_types::{WriteRef, Key, WriteType, TimeStamp};
+
+const STORE_CFS : [&str; 4] = ["default", "raft", "write", "lock"];
+
+//the keys of the transactional keyspaces are prefixed with it in API v2.
+const KEYSPACE_TXN_MODE_PREFIX : u8 = b'x';
+pub const MAX_KEYSPACE_ID : u32 = 0xFFFFFF;
+
+//where the data of a store is read from.
+enum StoreBackend {
+    Db(DB),
+    //the loose sst files of each cf, read without the MANIFEST.
+    SstFiles(HashMap<String, Vec<LooseSstFile>>),
+    //the data files of each cf in a BR backup.
+    BackupFiles(HashMap<String, Vec<BackupDataFile>>),
+    //the tablets of the partitioned raft kv, a rocksdb for each region.
+    Tablets(Vec<Tablet>),
 }
 
-impl RocksDbStorageNode {
+struct Tablet {
+    region_id : u64,
+    db : DB,
+    path : PathBuf,
+    titan_dir : Option<PathBuf>,
+    //the data key range of the region, the data out of it may be left by the split.
+    //it's known after the region meta are loaded from raft-engine.
+    data_key_range : Option<(Vec<u8>, Vec<u8>)>,
+}
+
+impl Tablet {
+    //the range of [lower_bound, upper_bound) in this tablet, None if they are not overlapped.
+    fn get_range(&self, lower_bound : &[u8], upper_bound : &[u8]) -> Option<(Vec<u8>, Vec<u8>)> {
+        let (start_key, end_key) = match self.data_key_range.as_ref() {
+            Some(r) => r,
+            None => return Some((lower_bound.to_vec(), upper_bound.to_vec())),
+        };
+        return region::intersect_ranges(&[(start_key.clone(), end_key.clone())], lower_bound, upper_bound).into_iter().next();
+    }
+}
+
+struct LooseSstFile {
+    path : PathBuf,
+    file_number : u64,
+    //not less than all the keys in the file, for skipping the files out of the range.
+    largest_key_bound : Option<Vec<u8>>,
+}
+
+//the rocksdb of a TiKV store.
+pub struct RocksDbStore {
+    backend : StoreBackend,
+    db_path : PathBuf,
+    //the large values of cf default are separated to the blob files if titan is enabled.
+    titan_dir : Option<PathBuf>,
+    //the ranges of the normal regions of this store, only the data in them will be read if it's set.
+    region_ranges : Option<Vec<(Vec<u8>, Vec<u8>)>>,
+    //(path, reason) of the sst files which can't be read.
+    skipped_sst_files : Vec<(PathBuf, String)>,
+    //the writes of the committed raft log entries which are not applied yet.
+    raft_changes : RaftChanges,
+    //the region meta read from raft-engine, for the stores which have none in the raft cf.
+    raft_log_regions : Vec<RegionInfo>,
+}
+
+impl RocksDbStore {
     pub fn new(db_path : &str) -> Result<Self, Error> {
         let db_path_buf = PathBuf::from(db_path); 
+        let opts = Self::get_open_options();
+        let db = match DB::open_cf_for_read_only(&opts, &db_path_buf, STORE_CFS.into_iter(), false) {
+            Ok(db) => db,
+            Err(e) => return Err(Error::StorageNodeError(format!("{}: {}", db_path, e.into_string()))),
+        };
+        
+        //XXX handle the errors
+
+        let titan_dir = Self::find_titan_dir(&db_path_buf);
+        Ok(RocksDbStore{
+            backend: StoreBackend::Db(db),
+            db_path: db_path_buf,
+            titan_dir: titan_dir,
+            region_ranges: None,
+            skipped_sst_files: vec![],
+            raft_changes: HashMap::new(),
+            raft_log_regions: vec![],
+        })
+    }
+
+    //copies the directory to the work directory and repairs the copy by rocksdb, the MANIFEST is
+    //rebuilt from the sst files there. the original directory is never touched. the missing cfs are
+    //created empty, and the sst files not in the repaired db are reported as skipped.
+    pub fn new_repaired(db_path : &str, work_path : &Path) -> Result<Self, Error> {
+        let db_path_buf = PathBuf::from(db_path);
+        //the work directory may not exist yet, its nearest existing ancestor is checked.
+        let work_ancestor = work_path.ancestors().find(|p| p.exists()).and_then(|p| p.canonicalize().ok());
+        if let (Ok(db_dir), Some(work_ancestor)) = (db_path_buf.canonicalize(), work_ancestor) {
+            if work_ancestor.starts_with(&db_dir) {
+                return Err(Error::StorageNodeError(format!("the work directory {} is in {}.", work_path.display(), db_path)));
+            }
+        }
+        if work_path.exists() {
+            return Err(Error::StorageNodeError(format!("the work directory {} already exists.", work_path.display())));
+        }
+
+        let mut skipped_sst_files = vec![];
+        copy_dir(&db_path_buf, work_path, &mut skipped_sst_files)?;
+        let copied_sst_files = list_sst_file_names(work_path)?;
+
+        let mut opts = Self::get_open_options();
+        if let Err(e) = DB::repair(&opts, work_path) {
+            return Err(Error::StorageNodeError(format!("repair {} error, {}", work_path.display(), e.into_string())));
+        }
+
+        //the copy is opened for writing to create the missing cfs, it's not compacted.
+        opts.create_missing_column_families(true);
+        opts.set_disable_auto_compactions(true);
+        let db = match DB::open_cf(&opts, work_path, STORE_CFS.into_iter()) {
+            Ok(db) => db,
+            Err(e) => return Err(Error::StorageNodeError(format!("{}: {}", work_path.display(), e.into_string()))),
+        };
+
+        let live_files = match db.live_files() {
+            Ok(f) => f,
+            Err(e) => return Err(Error::StorageNodeError(e.into_string())),
+        };
+        let live_file_names : HashSet<String> = live_files.iter().map(|f| f.name.trim_start_matches('/').to_string()).collect();
+        for name in copied_sst_files {
+            if !live_file_names.contains(&name) {
+                skipped_sst_files.push((db_path_buf.join(&name), "dropped by the repair.".to_string()));
+            }
+        }
+
+        let titan_dir = Self::find_titan_dir(work_path);
+        return Ok(RocksDbStore {
+            backend : StoreBackend::Db(db),
+            db_path : work_path.to_path_buf(),
+            titan_dir,
+            region_ranges : None,
+            skipped_sst_files,
+            raft_changes : HashMap::new(),
+            raft_log_regions : vec![],
+        });
+    }
+
+    fn get_open_options() -> Options {
         let mut opts = Options::default();
         opts.set_advise_random_on_open(false);
         opts.set_skip_checking_sst_file_sizes_on_db_open(true);
@@ -18,36 +160,319 @@ impl RocksDbStorageNode {
         let mut bopts = BlockBasedOptions::default();
         bopts.disable_cache();
         opts.set_block_based_table_factory(&bopts);
-        
-        let cfs = ["default", "raft", "write", "lock"];
-        let db = match DB::open_cf_for_read_only(&opts, &db_path_buf, cfs.into_iter(), false) {
-            Ok(db) => db,
-            Err(e) => return Err(Error::StorageNodeError(e.into_string())),
+        return opts;
+    }
+
+    //reads the sst files in the directory without opening the db, for the ones whose MANIFEST or CURRENT
+    //is damaged. the cf of a file is found in its properties, and the newest versions are chosen by the
+    //sequence numbers in the files. the files which can't be read are skipped.
+    pub fn new_from_sst_dir(sst_dir : &str) -> Result<Self, Error> {
+        let dir = PathBuf::from(sst_dir);
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(e) => e,
+            Err(e) => return Err(Error::StorageNodeError(format!("{}: {}", sst_dir, e.to_string()))),
         };
-        
-        //XXX handle the errors
 
-        Ok(RocksDbStorageNode{
-            db: db
-        })
+        let mut cf_files : HashMap<String, Vec<LooseSstFile>> = HashMap::new();
+        let mut skipped_sst_files = vec![];
+        for entry in entries {
+            let path = match entry {
+                Ok(e) => e.path(),
+                Err(e) => return Err(Error::IO(format!("read directory {} error, {}", sst_dir, e.to_string()))),
+            };
+            if !path.is_file() || path.extension().and_then(|e| e.to_str()) != Some("sst") {
+                continue;
+            }
+
+            let reader = match SstFileReader::open(&path) {
+                Ok(r) => r,
+                Err(e) => {
+                    skipped_sst_files.push((path, e.to_string()));
+                    continue;
+                },
+            };
+            let cf_name = match reader.get_column_family_name() {
+                Some(name) if !name.is_empty() => name,
+                _ => {
+                    skipped_sst_files.push((path, "the column family is unknown.".to_string()));
+                    continue;
+                },
+            };
+
+            let file_number = path.file_stem().and_then(|s| s.to_str()).and_then(|s| s.parse::<u64>().ok()).unwrap_or(0);
+            let largest_key_bound = reader.get_largest_key_bound().map(|k| k.to_vec());
+            cf_files.entry(cf_name).or_default().push(LooseSstFile { path, file_number, largest_key_bound });
+        }
+
+        if cf_files.is_empty() {
+            return Err(Error::StorageNodeError(format!("{}: no readable sst file.", sst_dir)));
+        }
+        //the larger file number is newer, for the same key with the same sequence number.
+        for files in cf_files.values_mut() {
+            files.sort_by(|a, b| b.file_number.cmp(&a.file_number));
+        }
+
+        let titan_dir = Self::find_titan_dir(&dir);
+        return Ok(RocksDbStore {
+            backend : StoreBackend::SstFiles(cf_files),
+            db_path : dir,
+            titan_dir,
+            region_ranges : None,
+            skipped_sst_files,
+            raft_changes : HashMap::new(),
+            raft_log_regions : vec![],
+        });
     }
 
-    pub fn get_table_data_iter<'a, 'b>(&'b self, table_info : &'a TableInfo) -> Result<TableDataIterator<'a, 'b>, Error> {
-        let default_cf_iter = match self.get_rocksdb_iter_by_cf_name(table_info.id, "default") {
-            Ok(i) => i,
-            Err(e) => return Err(Error::StorageNodeError(e.to_string())),
+    //the data files of a BR backup, the values are not separated by titan in them.
+    pub fn new_from_backup(backup_dir : &Path, data_files : Vec<BackupDataFile>) -> Self {
+        let mut cf_files : HashMap<String, Vec<BackupDataFile>> = HashMap::new();
+        for data_file in data_files {
+            cf_files.entry(data_file.cf.clone()).or_default().push(data_file);
+        }
+
+        return RocksDbStore {
+            backend : StoreBackend::BackupFiles(cf_files),
+            db_path : backup_dir.to_path_buf(),
+            titan_dir : None,
+            region_ranges : None,
+            skipped_sst_files : vec![],
+            raft_changes : HashMap::new(),
+            raft_log_regions : vec![],
         };
-        let write_cf_iter = match self.get_rocksdb_iter_by_cf_name(table_info.id, "write") {
-            Ok(i) => i,
-            Err(e) => return Err(Error::StorageNodeError(e.to_string())),
+    }
+
+    //the tablets of the partitioned raft kv in the directory, which are named {region id}_{tablet index}.
+    //the latest tablet of each region is opened read only, the temporary ones with prefixes are skipped.
+    pub fn new_from_tablets(tablets_dir : &str) -> Result<Self, Error> {
+        let dir = PathBuf::from(tablets_dir);
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(e) => e,
+            Err(e) => return Err(Error::StorageNodeError(format!("{}: {}", tablets_dir, e.to_string()))),
         };
 
-        return Ok(TableDataIterator::new(table_info, default_cf_iter, write_cf_iter));
+        //region id -> (tablet index, path)
+        let mut latest_tablets : HashMap<u64, (u64, PathBuf)> = HashMap::new();
+        for entry in entries {
+            let path = match entry {
+                Ok(e) => e.path(),
+                Err(e) => return Err(Error::IO(format!("read directory {} error, {}", tablets_dir, e.to_string()))),
+            };
+            if !path.is_dir() {
+                continue;
+            }
+            let (region_id, tablet_index) = match path.file_name().and_then(|n| n.to_str()).and_then(parse_tablet_name) {
+                Some(r) => r,
+                None => continue,
+            };
+            match latest_tablets.get(&region_id) {
+                Some((index, _)) if *index >= tablet_index => (),
+                _ => {
+                    latest_tablets.insert(region_id, (tablet_index, path));
+                },
+            }
+        }
+
+        if latest_tablets.is_empty() {
+            return Err(Error::StorageNodeError(format!("{}: no tablet found.", tablets_dir)));
+        }
+
+        let opts = Self::get_open_options();
+        let mut tablets = Vec::with_capacity(latest_tablets.len());
+        for (region_id, (_, path)) in latest_tablets {
+            //the tablets have no raft cf.
+            let cf_names = match DB::list_cf(&opts, &path) {
+                Ok(names) => names,
+                Err(e) => return Err(Error::StorageNodeError(format!("{}: {}", path.display(), e.into_string()))),
+            };
+            let db = match DB::open_cf_for_read_only(&opts, &path, cf_names, false) {
+                Ok(db) => db,
+                Err(e) => return Err(Error::StorageNodeError(format!("{}: {}", path.display(), e.into_string()))),
+            };
+            let titan_dir = Self::find_titan_dir(&path);
+            tablets.push(Tablet { region_id, db, path, titan_dir, data_key_range : None });
+        }
+        tablets.sort_by_key(|t| t.region_id);
+
+        return Ok(RocksDbStore {
+            backend : StoreBackend::Tablets(tablets),
+            db_path : dir,
+            titan_dir : None,
+            region_ranges : None,
+            skipped_sst_files : vec![],
+            raft_changes : HashMap::new(),
+            raft_log_regions : vec![],
+        });
     }
 
-    fn get_rocksdb_iter_by_cf_name<'a> (&'a self, table_id : i64, cf_name : &str) -> Result<DBIterator<'a>, Error> {
-        let (lower_bound, upper_bound) = Self::get_table_data_keys_by_table_id(table_id);
-        
+    fn find_titan_dir(db_path : &Path) -> Option<PathBuf> {
+        let titan_dir = db_path.join(titan::TITAN_DIR_NAME);
+        if titan_dir.is_dir() {
+            return Some(titan_dir);
+        }
+        return None;
+    }
+
+    pub fn get_skipped_sst_files(&self) -> &[(PathBuf, String)] {
+        return &self.skipped_sst_files;
+    }
+
+    pub fn get_path(&self) -> &Path {
+        return &self.db_path;
+    }
+
+    //skip the data left in the key ranges which are not owned by this store anymore,
+    //such as the tombstone regions or the regions applying snapshots.
+    pub fn limit_to_normal_regions(&mut self) -> Result<(), Error> {
+        let regions = self.get_regions()?;
+        //no region meta at all, it may not be the data directory of a TiKV store.
+        if regions.is_empty() {
+            return Ok(());
+        }
+
+        let mut region_ranges : Vec<(Vec<u8>, Vec<u8>)> = regions.iter()
+            .filter(|r| r.is_normal())
+            .map(|r| r.get_data_key_range())
+            .collect();
+        region_ranges.sort();
+        self.region_ranges = Some(region_ranges);
+        return Ok(());
+    }
+
+    //if the data key will be read from this store.
+    pub fn is_key_readable(&self, data_key : &[u8]) -> bool {
+        return match self.region_ranges.as_ref() {
+            None => true,
+            Some(region_ranges) => region_ranges.iter().any(|(start_key, end_key)| {
+                start_key.as_slice() <= data_key && data_key < end_key.as_slice()
+            }),
+        };
+    }
+
+    pub fn is_titan_enabled(&self) -> bool {
+        if let StoreBackend::Tablets(tablets) = &self.backend {
+            return tablets.iter().any(|t| t.titan_dir.is_some());
+        }
+        return self.titan_dir.is_some();
+    }
+
+    //the value of cf default which is only in the memtable(WAL) of titan, it's not in the sst files
+    //read by get_titan_cf_iter. the memtable keeps the real values, they are separated by the flush.
+    pub fn get_titan_memtable_value(&self, key : &[u8]) -> Result<Option<Box<[u8]>>, Error> {
+        let dbs : Vec<&DB> = match &self.backend {
+            StoreBackend::Db(db) if self.titan_dir.is_some() => vec![db],
+            StoreBackend::Tablets(tablets) => tablets.iter().filter(|t| t.titan_dir.is_some()).map(|t| &t.db).collect(),
+            _ => vec![],
+        };
+
+        for db in dbs {
+            let cf = match db.cf_handle("default") {
+                Some(cf) => cf,
+                None => continue,
+            };
+            match db.get_cf(cf, key) {
+                Ok(Some(v)) => return Ok(Some(v.into_boxed_slice())),
+                Ok(None) => (),
+                Err(e) => return Err(Error::StorageNodeError(e.into_string())),
+            }
+        }
+        return Ok(None);
+    }
+
+    //iterates the keys in [lower_bound, upper_bound) of the cf, limited by the regions if needed.
+    //the sorted ranges are read one by one, the iterator of a range is created when reaching it.
+    pub fn get_region_cf_iter<'a>(&'a self, cf_name : &'a str, lower_bound : &[u8], upper_bound : &[u8]) -> KvIterator<'a> {
+        let ranges = match self.region_ranges.as_ref() {
+            None => vec![(lower_bound.to_vec(), upper_bound.to_vec())],
+            Some(region_ranges) => region::intersect_ranges(region_ranges, lower_bound, upper_bound),
+        };
+
+        return Box::new(ranges.into_iter().flat_map(move |(lower_bound, upper_bound)| {
+            let iter : KvIterator<'a> = match self.get_cf_iter(cf_name, &lower_bound, &upper_bound) {
+                Ok(iter) => iter,
+                Err(e) => Box::new(std::iter::once(Err(e))),
+            };
+            iter
+        }));
+    }
+
+    //iterates the keys in [lower_bound, upper_bound) of the cf, the writes replayed from the raft log are applied.
+    pub fn get_cf_iter<'a>(&'a self, cf_name : &str, lower_bound : &[u8], upper_bound : &[u8]) -> Result<KvIterator<'a>, Error> {
+        let iter = self.get_base_cf_iter(cf_name, lower_bound, upper_bound)?;
+        let cf_changes = match self.raft_changes.get(cf_name) {
+            Some(c) if !c.is_empty() => c,
+            _ => return Ok(iter),
+        };
+        if !upper_bound.is_empty() && lower_bound >= upper_bound {
+            return Ok(iter);
+        }
+
+        let upper = if upper_bound.is_empty() {
+            Bound::Unbounded
+        } else {
+            Bound::Excluded(upper_bound)
+        };
+        let changes = cf_changes.range::<[u8], _>((Bound::Included(lower_bound), upper))
+            .map(|(k, v)| (Box::from(k.as_slice()), v.clone()))
+            .collect();
+        return Ok(Box::new(OverlayKvIterator::new(iter, changes)));
+    }
+
+    //iterates the keys in [lower_bound, upper_bound) of the cf stored in the backend.
+    fn get_base_cf_iter<'a>(&'a self, cf_name : &str, lower_bound : &[u8], upper_bound : &[u8]) -> Result<KvIterator<'a>, Error> {
+        let db = match &self.backend {
+            StoreBackend::Db(db) => db,
+            StoreBackend::SstFiles(cf_files) => {
+                let paths = match cf_files.get(cf_name) {
+                    Some(files) => files.iter()
+                        .filter(|f| f.largest_key_bound.as_ref().map_or(false, |k| k.as_slice() >= lower_bound))
+                        .map(|f| f.path.clone())
+                        .collect(),
+                    //no file of the cf is found.
+                    None => vec![],
+                };
+                return Self::get_sst_files_iter(paths, self.titan_dir.as_deref(), cf_name, lower_bound, upper_bound);
+            },
+            StoreBackend::BackupFiles(cf_files) => {
+                //the keys in the backup files are not prefixed with 'z'.
+                let lower_bound = lower_bound.strip_prefix(keys::DATA_PREFIX_KEY).unwrap_or(lower_bound);
+                let upper_bound = upper_bound.strip_prefix(keys::DATA_PREFIX_KEY).unwrap_or(upper_bound);
+                let paths = match cf_files.get(cf_name) {
+                    Some(files) => files.iter()
+                        .filter(|f| f.end_key.is_empty() || f.end_key.as_slice() > lower_bound)
+                        .filter(|f| upper_bound.is_empty() || f.start_key.as_slice() < upper_bound)
+                        .map(|f| f.path.clone())
+                        .collect(),
+                    None => vec![],
+                };
+                let iter = Self::get_sst_files_iter(paths, self.titan_dir.as_deref(), cf_name, lower_bound, upper_bound)?;
+                return Ok(Box::new(iter.map(|res| res.map(|(key_data, val_data)| (keys::data_key(&key_data).into_boxed_slice(), val_data)))));
+            },
+            StoreBackend::Tablets(tablets) => {
+                let mut iters = vec![];
+                for tablet in tablets {
+                    let (lower_bound, upper_bound) = match tablet.get_range(lower_bound, upper_bound) {
+                        Some(r) => r,
+                        None => continue,
+                    };
+                    //the cf which is not created in the tablet has no data.
+                    if tablet.db.cf_handle(cf_name).is_none() {
+                        continue;
+                    }
+                    iters.push(Self::get_db_cf_iter(&tablet.db, &tablet.path, tablet.titan_dir.as_deref(), cf_name, &lower_bound, &upper_bound)?);
+                }
+                return Ok(Box::new(MergedKvIterator::new(iters)));
+            },
+        };
+
+        return Self::get_db_cf_iter(db, &self.db_path, self.titan_dir.as_deref(), cf_name, lower_bound, upper_bound);
+    }
+
+    fn get_db_cf_iter<'a>(db : &'a DB, db_path : &Path, titan_dir : Option<&Path>, cf_name : &str, lower_bound : &[u8], upper_bound : &[u8]) -> Result<KvIterator<'a>, Error> {
+        if cf_name == "default" && titan_dir.is_some() {
+            return Self::get_titan_cf_iter(db, db_path, titan_dir, cf_name, lower_bound, upper_bound);
+        }
+
         let mut readopts = rocksdb::ReadOptions::default();
         //4MB
         readopts.set_readahead_size(4 * 1024 * 1024);
@@ -55,38 +480,545 @@ impl RocksDbStorageNode {
         readopts.set_iterate_lower_bound(lower_bound);
         readopts.set_iterate_upper_bound(upper_bound);
 
-        let cf = match self.db.cf_handle(cf_name) {
+        let cf = match db.cf_handle(cf_name) {
             Some(cf) => cf,
             None => return Err(Error::StorageNodeError(format!("cf {} not exists.", cf_name))),
         };
 
-        let iter = self.db.iterator_cf_opt(cf, readopts, rocksdb::IteratorMode::Start);
-        return Ok(iter);
+        let iter = db.iterator_cf_opt(cf, readopts, rocksdb::IteratorMode::Start)
+            .map(|res| res.map_err(|e| Error::StorageNodeError(e.into_string())));
+        return Ok(Box::new(iter));
     }
 
-    pub fn get_databases(&self) -> Result<Vec<DBInfo>, Error>{
-        // Keys used to scan database records
-        let (lower_bound, upper_bound) = ([b'z', b'm', b'D', b'B', b's'], [b'z', b'm', b'D', b'B', b's', 0xff]);
-        
-        let mut readopts = rocksdb::ReadOptions::default();
-        readopts.set_iterate_lower_bound(lower_bound);
-        readopts.set_iterate_upper_bound(upper_bound);
+    //the iterator of rocksdb returns the blob indexes of titan as errors, so the sst files
+    //are read and merged by ourselves, the values are fetched from the blob files.
+    //the data only in the memtable(WAL) is invisible in this way, it's looked up by get_titan_memtable_value.
+    fn get_titan_cf_iter<'a>(db : &DB, db_path : &Path, titan_dir : Option<&Path>, cf_name : &str, lower_bound : &[u8], upper_bound : &[u8]) -> Result<KvIterator<'a>, Error> {
+        let live_files = match db.live_files() {
+            Ok(f) => f,
+            Err(e) => return Err(Error::StorageNodeError(e.into_string())),
+        };
+
+        let mut files : Vec<(i32, u64, String)> = vec![];
+        for f in live_files {
+            if f.column_family_name != cf_name {
+                continue;
+            }
+            //skip the files out of the range.
+            if let Some(end_key) = f.end_key.as_ref() {
+                if end_key.as_slice() < lower_bound {
+                    continue;
+                }
+            }
+            if let Some(start_key) = f.start_key.as_ref() {
+                if !upper_bound.is_empty() && start_key.as_slice() >= upper_bound {
+                    continue;
+                }
+            }
 
-        let default_cf = match self.db.cf_handle("write") {
+            let file_number = f.name.trim_start_matches('/').trim_end_matches(".sst").parse::<u64>().unwrap_or(0);
+            files.push((f.level, file_number, f.name));
+        }
+
+        //newest first, the lower level is newer and the larger file number is newer in level 0.
+        files.sort_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)));
+
+        let paths = files.into_iter().map(|(_, _, name)| db_path.join(name.trim_start_matches('/'))).collect();
+        return Self::get_sst_files_iter(paths, titan_dir, cf_name, lower_bound, upper_bound);
+    }
+
+    //merges the sst files which are sorted from the newest to the oldest, the blob indexes
+    //in cf default are resolved by the blob files if titan is enabled.
+    fn get_sst_files_iter<'a>(paths : Vec<PathBuf>, titan_dir : Option<&Path>, cf_name : &str, lower_bound : &[u8], upper_bound : &[u8]) -> Result<KvIterator<'a>, Error> {
+        let mut iters = Vec::with_capacity(paths.len());
+        for path in paths {
+            let reader = SstFileReader::open(&path)?;
+            iters.push(reader.into_iter_range(lower_bound, upper_bound));
+        }
+
+        let merged = SstMergeIterator::new(iters);
+        if cf_name == "default" {
+            if let Some(titan_dir) = titan_dir {
+                return Ok(Box::new(TitanKvIterator::new(merged, TitanBlobReader::new(titan_dir))));
+            }
+        }
+        return Ok(Box::new(SstKvIterator::new(merged)));
+    }
+
+    fn get_cf_value(&self, cf_name : &str, key : &[u8]) -> Result<Option<Vec<u8>>, Error> {
+        if let Some(change) = self.raft_changes.get(cf_name).and_then(|c| c.get(key)) {
+            return Ok(change.as_ref().map(|v| v.to_vec()));
+        }
+
+        let db = match &self.backend {
+            StoreBackend::Db(db) => db,
+            StoreBackend::SstFiles(_) | StoreBackend::BackupFiles(_) | StoreBackend::Tablets(_) => {
+                let mut upper_bound = key.to_vec();
+                upper_bound.push(0);
+                for item_res in self.get_cf_iter(cf_name, key, &upper_bound)? {
+                    let (key_data, val_data) = item_res?;
+                    if key_data.as_ref() == key {
+                        return Ok(Some(val_data.to_vec()));
+                    }
+                }
+                return Ok(None);
+            },
+        };
+
+        let cf = match db.cf_handle(cf_name) {
             Some(cf) => cf,
-            None => return Err(Error::StorageNodeError("cf write not exists.".to_string())),
+            None => return Err(Error::StorageNodeError(format!("cf {} not exists.", cf_name))),
         };
-        
-        let iter = self.db.iterator_cf_opt(&default_cf, readopts, rocksdb::IteratorMode::Start);
+
+        return match db.get_cf(cf, key) {
+            Ok(v) => Ok(v),
+            Err(e) => Err(Error::StorageNodeError(e.into_string())),
+        };
+    }
+
+    pub fn get_store_id(&self) -> Result<Option<u64>, Error> {
+        let ident_data = match self.get_cf_value("default", keys::STORE_IDENT_KEY)? {
+            Some(d) => d,
+            None => return Ok(None),
+        };
+
+        let mut ident = kvproto::raft_serverpb::StoreIdent::default();
+        if protobuf::Message::merge_from_bytes(&mut ident, &ident_data).is_err() {
+            return Err(Error::CorruptedDataBytes("parse StoreIdent error.".to_string(), ident_data.into_boxed_slice()));
+        }
+        return Ok(Some(ident.get_store_id()));
+    }
+
+    //the regions whose meta are in the raft cf of this store, in any state.
+    //they are the ones read from raft-engine if there is none in the raft cf.
+    pub fn get_regions(&self) -> Result<Vec<RegionInfo>, Error> {
+        let mut ret = vec![];
+        for item_res in self.get_cf_iter("raft", keys::REGION_META_MIN_KEY, keys::REGION_META_MAX_KEY)? {
+            let (key_data, val_data) = item_res?;
+            let (region_id, suffix) = match keys::decode_region_meta_key(key_data.as_ref()) {
+                Ok(r) => r,
+                Err(_) => return Err(Error::CorruptedDataBytes("decode region meta key error.".to_string(), key_data)),
+            };
+            if suffix != keys::REGION_STATE_SUFFIX {
+                continue;
+            }
+
+            let local_state = region::parse_region_local_state(val_data.as_ref())?;
+            let apply_state = match self.get_cf_value("raft", &keys::apply_state_key(region_id))? {
+                Some(d) => Some(region::parse_raft_apply_state(&d)?),
+                None => None,
+            };
+            ret.push(RegionInfo { local_state, apply_state });
+        }
+
+        if ret.is_empty() {
+            return Ok(self.raft_log_regions.clone());
+        }
+        return Ok(ret);
+    }
+
+    //the user keys in (lower_bound, upper_bound) where the data can be split, the start keys of the regions
+    //and the smallest keys of the files. the timestamps are truncated, so the versions of a key are not split.
+    pub fn get_split_keys(&self, cf_name : &str, lower_bound : &[u8], upper_bound : &[u8]) -> Result<Vec<Vec<u8>>, Error> {
+        let mut ret : Vec<Vec<u8>> = self.get_regions()?.iter().map(|r| r.get_data_key_range().0).collect();
+        let mut file_keys = vec![];
+        match &self.backend {
+            StoreBackend::Db(db) => file_keys = get_live_file_start_keys(db, cf_name)?,
+            StoreBackend::Tablets(tablets) => for tablet in tablets {
+                file_keys.extend(get_live_file_start_keys(&tablet.db, cf_name)?);
+            },
+            //the bounds of the loose files may not be the real keys.
+            StoreBackend::SstFiles(_) => (),
+            //the ranges of the backup files have no timestamp.
+            StoreBackend::BackupFiles(cf_files) => if let Some(files) = cf_files.get(cf_name) {
+                ret.extend(files.iter().filter(|f| !f.start_key.is_empty()).map(|f| keys::data_key(&f.start_key)));
+            },
+        }
+        for key in file_keys {
+            if let Ok(user_key) = Key::truncate_ts_for(key.get(1..).unwrap_or_default()) {
+                ret.push(keys::data_key(user_key));
+            }
+        }
+
+        ret.retain(|k| k.as_slice() > lower_bound && k.as_slice() < upper_bound);
+        return Ok(ret);
+    }
+
+    //the tablets are limited to the ranges of their regions by the loaded region meta.
+    pub fn load_raft_log_regions(&mut self, raft_log : &RaftLogStore) -> Result<(), Error> {
+        self.raft_log_regions = raft_log.get_regions()?;
+        if let StoreBackend::Tablets(tablets) = &mut self.backend {
+            for tablet in tablets.iter_mut() {
+                tablet.data_key_range = self.raft_log_regions.iter()
+                    .find(|r| r.get_id() == tablet.region_id)
+                    .map(|r| r.get_data_key_range());
+                if tablet.data_key_range.is_none() {
+                    print!("warning: the region meta of tablet {} is not found, it's read in its full range.\n", tablet.path.display());
+                }
+            }
+        }
+        return Ok(());
+    }
+
+    //the committed entries after the applied index of the normal regions are replayed,
+    //their writes are read along with the data in the backend.
+    pub fn replay_raft_log(&mut self, raft_log : &RaftLogStore) -> Result<RaftReplaySummary, Error> {
+        let mut changes = RaftChanges::new();
+        let mut summary = RaftReplaySummary::default();
+        for region in self.get_regions()? {
+            if !region.is_normal() {
+                continue;
+            }
+            let applied_index = match region.apply_state.as_ref() {
+                Some(s) => s.get_applied_index(),
+                None => continue,
+            };
+            raftlog::replay_region(raft_log, region.get_id(), applied_index, &mut changes, &mut summary)?;
+        }
+
+        self.raft_changes = changes;
+        return Ok(summary);
+    }
+}
+
+//copies the files of the directory recursively, the sst files which can't be copied are skipped.
+fn copy_dir(src : &Path, dst : &Path, skipped_sst_files : &mut Vec<(PathBuf, String)>) -> Result<(), Error> {
+    if let Err(e) = std::fs::create_dir_all(dst) {
+        return Err(Error::IO(format!("create directory {} error, {}", dst.display(), e.to_string())));
+    }
+    let entries = match std::fs::read_dir(src) {
+        Ok(e) => e,
+        Err(e) => return Err(Error::IO(format!("read directory {} error, {}", src.display(), e.to_string()))),
+    };
+
+    for entry in entries {
+        let path = match entry {
+            Ok(e) => e.path(),
+            Err(e) => return Err(Error::IO(format!("read directory {} error, {}", src.display(), e.to_string()))),
+        };
+        let dst_path = dst.join(path.file_name().unwrap_or_default());
+        if path.is_dir() {
+            copy_dir(&path, &dst_path, skipped_sst_files)?;
+            continue;
+        }
+        if let Err(e) = std::fs::copy(&path, &dst_path) {
+            if path.extension().and_then(|e| e.to_str()) == Some("sst") {
+                skipped_sst_files.push((path, format!("copy error, {}", e.to_string())));
+                continue;
+            }
+            return Err(Error::IO(format!("copy {} error, {}", path.display(), e.to_string())));
+        }
+    }
+    return Ok(());
+}
+
+fn get_live_file_start_keys(db : &DB, cf_name : &str) -> Result<Vec<Vec<u8>>, Error> {
+    let live_files = match db.live_files() {
+        Ok(f) => f,
+        Err(e) => return Err(Error::StorageNodeError(e.into_string())),
+    };
+    return Ok(live_files.into_iter()
+        .filter(|f| f.column_family_name == cf_name)
+        .filter_map(|f| f.start_key)
+        .collect());
+}
+
+//{region id}_{tablet index}, the temporary tablets are prefixed, such as split_{region id}_{tablet index}.
+fn parse_tablet_name(name : &str) -> Option<(u64, u64)> {
+    let (region_id, tablet_index) = name.split_once('_')?;
+    return Some((region_id.parse::<u64>().ok()?, tablet_index.parse::<u64>().ok()?));
+}
+
+fn list_sst_file_names(dir : &Path) -> Result<Vec<String>, Error> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(e) => e,
+        Err(e) => return Err(Error::IO(format!("read directory {} error, {}", dir.display(), e.to_string()))),
+    };
+    let mut ret = vec![];
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name.ends_with(".sst") {
+            ret.push(name);
+        }
+    }
+    return Ok(ret);
+}
+
+//the TiKV stores to read, the data of them are merged by key.
+pub struct RocksDbStorageNode {
+    stores : Vec<RocksDbStore>,
+    //the schemas are read from the backupmeta instead of the meta keys for the BR backups.
+    backup_schemas : Option<BackupSchemas>,
+    //only the data committed before or at this ts will be read.
+    read_ts : TimeStamp,
+    //include the data of the pending locks whose transactions are committed.
+    resolve_locks : bool,
+    //'x' and the 3 bytes id of the keyspace for API v2, prepended to all the keys of TiDB.
+    keyspace_prefix : Vec<u8>,
+}
+
+impl RocksDbStorageNode {
+    pub fn new(db_paths : &[String]) -> Result<Self, Error> {
+        let mut stores = Vec::with_capacity(db_paths.len());
+        for db_path in db_paths {
+            stores.push(RocksDbStore::new(db_path)?);
+        }
+        return Ok(Self::from_stores(stores));
+    }
+
+    //the stores are repaired in the sub directories of the work directory, one for each.
+    pub fn new_repaired(db_paths : &[String], work_dir : &str) -> Result<Self, Error> {
+        let mut stores = Vec::with_capacity(db_paths.len());
+        for (i, db_path) in db_paths.iter().enumerate() {
+            let work_path = Path::new(work_dir).join(format!("store-{}", i + 1));
+            stores.push(RocksDbStore::new_repaired(db_path, &work_path)?);
+        }
+        return Ok(Self::from_stores(stores));
+    }
+
+    //the stores are read from the directories of the loose sst files.
+    pub fn new_from_sst_dirs(sst_dirs : &[String]) -> Result<Self, Error> {
+        let mut stores = Vec::with_capacity(sst_dirs.len());
+        for sst_dir in sst_dirs {
+            stores.push(RocksDbStore::new_from_sst_dir(sst_dir)?);
+        }
+        return Ok(Self::from_stores(stores));
+    }
+
+    //the stores are read from the tablets directories of the partitioned raft kv, one for each store.
+    pub fn new_from_tablet_dirs(tablets_dirs : &[String]) -> Result<Self, Error> {
+        let mut stores = Vec::with_capacity(tablets_dirs.len());
+        for tablets_dir in tablets_dirs {
+            stores.push(RocksDbStore::new_from_tablets(tablets_dir)?);
+        }
+        return Ok(Self::from_stores(stores));
+    }
+
+    //the stores are read from the directories of the BR backups.
+    pub fn new_from_backup_dirs(backup_dirs : &[String]) -> Result<Self, Error> {
+        let mut stores = Vec::with_capacity(backup_dirs.len());
+        let mut schemas = BackupSchemas::default();
+        for backup_dir in backup_dirs {
+            let dir = Path::new(backup_dir);
+            if !backup::is_backup_dir(dir) {
+                return Err(Error::StorageNodeError(format!("{}: no backupmeta found.", backup_dir)));
+            }
+            let info = backup::read_backup_info(dir)?;
+            schemas.merge(info.schemas);
+            stores.push(RocksDbStore::new_from_backup(dir, info.data_files));
+        }
+
+        let mut node = Self::from_stores(stores);
+        node.backup_schemas = Some(schemas);
+        return Ok(node);
+    }
+
+    fn from_stores(stores : Vec<RocksDbStore>) -> Self {
+        return RocksDbStorageNode {
+            stores,
+            backup_schemas : None,
+            read_ts : TimeStamp::max(),
+            resolve_locks : false,
+            keyspace_prefix : vec![],
+        };
+    }
+
+    pub fn get_stores(&self) -> &[RocksDbStore] {
+        return &self.stores;
+    }
+
+    pub fn set_keyspace(&mut self, keyspace_id : u32) {
+        self.keyspace_prefix = get_keyspace_prefix(keyspace_id).to_vec();
+    }
+
+    pub fn set_read_ts(&mut self, read_ts : TimeStamp) {
+        self.read_ts = read_ts;
+    }
+
+    pub fn get_read_ts(&self) -> TimeStamp {
+        return self.read_ts;
+    }
+
+    pub fn set_resolve_locks(&mut self, resolve_locks : bool) {
+        self.resolve_locks = resolve_locks;
+    }
+
+    pub fn limit_to_normal_regions(&mut self) -> Result<(), Error> {
+        for store in self.stores.iter_mut() {
+            store.limit_to_normal_regions()?;
+        }
+        return Ok(());
+    }
+
+    //the raft logs of the stores are in the paths, in the same order as the stores. the region meta
+    //in raft-engine are loaded, and the committed entries are replayed if `replay` is true.
+    //raft-engine is copied to the work directory, or a temporary directory removed after reading.
+    pub fn load_raft_logs(&mut self, raft_paths : &[PathBuf], work_dir : Option<&str>, replay : bool) -> Result<Vec<RaftReplaySummary>, Error> {
+        if raft_paths.len() != self.stores.len() {
+            return Err(Error::StorageNodeError(format!("{} raft paths are specified for {} stores.", raft_paths.len(), self.stores.len())));
+        }
+
+        let mut ret = Vec::with_capacity(self.stores.len());
+        for (i, (store, raft_path)) in self.stores.iter_mut().zip(raft_paths).enumerate() {
+            if !raftlog::is_raft_engine_dir(raft_path) {
+                let raft_log = RaftLogStore::open(raft_path)?;
+                if replay {
+                    ret.push(store.replay_raft_log(&raft_log)?);
+                }
+                continue;
+            }
+
+            let copy_path = match work_dir {
+                Some(d) => Path::new(d).join(format!("raft-engine-{}", i + 1)),
+                None => std::env::temp_dir().join(format!("tidb-exporter-{}-raft-engine-{}", std::process::id(), i + 1)),
+            };
+            if copy_path.exists() {
+                return Err(Error::StorageNodeError(format!("the directory {} already exists.", copy_path.display())));
+            }
+            let res = Self::load_raft_engine(store, raft_path, &copy_path, replay);
+            if work_dir.is_none() {
+                let _ = std::fs::remove_dir_all(&copy_path);
+            }
+            if let Some(summary) = res? {
+                ret.push(summary);
+            }
+        }
+        return Ok(ret);
+    }
+
+    fn load_raft_engine(store : &mut RocksDbStore, raft_path : &Path, copy_path : &Path, replay : bool) -> Result<Option<RaftReplaySummary>, Error> {
+        copy_dir(raft_path, copy_path, &mut vec![])?;
+        let raft_log = RaftLogStore::open_raft_engine(copy_path)?;
+        store.load_raft_log_regions(&raft_log)?;
+        if !replay {
+            return Ok(None);
+        }
+        return Ok(Some(store.replay_raft_log(&raft_log)?));
+    }
+
+    //the indexes of the stores which the raw key should be read from, but it's not in the sources.
+    pub fn get_missing_stores(&self, raw_key : &[u8], sources : &[usize]) -> Vec<usize> {
+        let data_key = self.encode_data_key(raw_key);
+        return (0..self.stores.len())
+            .filter(|idx| !sources.contains(idx) && self.stores[*idx].is_key_readable(&data_key))
+            .collect();
+    }
+
+    pub fn get_table_data_iter<'a, 'b>(&'b self, table_info : &'a TableInfo) -> Result<TableDataIterator<'a, 'b>, Error> {
+        let (lower_bound, upper_bound) = self.get_table_data_keys_by_table_id(table_info.id);
+        return self.get_table_data_range_iter(table_info, &lower_bound, &upper_bound);
+    }
+
+    //the rows of the table in a range returned by get_table_data_split_ranges.
+    pub fn get_table_data_range_iter<'a, 'b>(&'b self, table_info : &'a TableInfo, lower_bound : &[u8], upper_bound : &[u8]) -> Result<TableDataIterator<'a, 'b>, Error> {
+        return TableDataIterator::new(table_info, self.get_mvcc_iter(lower_bound, upper_bound)?);
+    }
+
+    //splits the data of the table into at most range_num ranges in the order of keys, by the split keys
+    //picked evenly from the ones of the stores. fewer ranges are returned if there are not enough split keys.
+    pub fn get_table_data_split_ranges(&self, table_info : &TableInfo, range_num : usize) -> Result<Vec<(Vec<u8>, Vec<u8>)>, Error> {
+        let (lower_bound, upper_bound) = self.get_table_data_keys_by_table_id(table_info.id);
+        let mut split_keys = vec![];
+        for store in self.stores.iter() {
+            split_keys.extend(store.get_split_keys("write", &lower_bound, &upper_bound)?);
+        }
+        split_keys.sort();
+        split_keys.dedup();
+
+        let mut ret = Vec::with_capacity(range_num);
+        let mut start_key = lower_bound;
+        for i in 1..range_num {
+            let split_key = match split_keys.get(i * split_keys.len() / range_num) {
+                Some(k) => k,
+                None => break,
+            };
+            if *split_key <= start_key {
+                continue;
+            }
+            ret.push((start_key, split_key.clone()));
+            start_key = split_key.clone();
+        }
+        ret.push((start_key, upper_bound));
+        return Ok(ret);
+    }
+
+    pub fn get_index_data_iter<'a, 'b>(&'b self, table_info : &'a TableInfo, index_info : &'a IndexInfo) -> Result<IndexDataIterator<'a, 'b>, Error> {
+        let (lower_bound, upper_bound) = self.get_index_data_keys_by_index_id(table_info.id, index_info.id);
+        return Ok(IndexDataIterator::new(table_info, index_info, self.get_mvcc_iter(&lower_bound, &upper_bound)?));
+    }
+
+    //the latest visible versions in [lower_bound, upper_bound) of all the stores.
+    //the write cf iterators of the stores are merged, the indexes of the sources are the same as
+    //the stores, the committed writes of the locks are the last one if they are resolved.
+    pub fn get_mvcc_iter<'a>(&'a self, lower_bound : &[u8], upper_bound : &[u8]) -> Result<MvccIterator<'a>, Error> {
+        let default_cf_iter = self.get_region_cf_iter("default", lower_bound, upper_bound);
+
+        let mut write_cf_iters : Vec<KvIterator<'a>> = self.stores.iter()
+            .map(|store| store.get_region_cf_iter("write", lower_bound, upper_bound))
+            .collect();
+        if self.resolve_locks {
+            let committed_writes = LockResolver::new(self).get_committed_writes(lower_bound, upper_bound)?;
+            write_cf_iters.push(Box::new(committed_writes.into_iter().map(Ok)));
+        }
+
+        let mut mvcc_iter = MvccIterator::new(default_cf_iter, MergedKvIterator::new(write_cf_iters), self.read_ts);
+        mvcc_iter.set_keyspace_prefix(&self.keyspace_prefix);
+        if self.stores.iter().any(|store| store.is_titan_enabled()) {
+            mvcc_iter.set_default_value_fallback(Box::new(move |key : &[u8]| self.get_titan_memtable_value(key)));
+        }
+        return Ok(mvcc_iter);
+    }
+
+    //the value of cf default in the memtables of the titan stores, which is not read by the iterators.
+    fn get_titan_memtable_value(&self, key : &[u8]) -> Result<Option<Box<[u8]>>, Error> {
+        for store in self.stores.iter() {
+            if let Some(v) = store.get_titan_memtable_value(key)? {
+                return Ok(Some(v));
+            }
+        }
+        return Ok(None);
+    }
+
+    //iterates the keys in [lower_bound, upper_bound) of the cf in all the stores, limited by the regions if needed.
+    pub fn get_region_cf_iter<'a>(&'a self, cf_name : &'a str, lower_bound : &[u8], upper_bound : &[u8]) -> KvIterator<'a> {
+        if self.stores.len() == 1 {
+            return self.stores[0].get_region_cf_iter(cf_name, lower_bound, upper_bound);
+        }
+
+        let iters = self.stores.iter()
+            .map(|store| store.get_region_cf_iter(cf_name, lower_bound, upper_bound))
+            .collect();
+        return Box::new(MergedKvIterator::new(iters));
+    }
+
+    //iterates the keys in [lower_bound, upper_bound) of the cf in all the stores.
+    pub fn get_cf_iter<'a>(&'a self, cf_name : &str, lower_bound : &[u8], upper_bound : &[u8]) -> Result<KvIterator<'a>, Error> {
+        if self.stores.len() == 1 {
+            return self.stores[0].get_cf_iter(cf_name, lower_bound, upper_bound);
+        }
+
+        let mut iters = Vec::with_capacity(self.stores.len());
+        for store in self.stores.iter() {
+            iters.push(store.get_cf_iter(cf_name, lower_bound, upper_bound)?);
+        }
+        return Ok(Box::new(MergedKvIterator::new(iters)));
+    }
+
+    pub fn get_databases(&self) -> Result<Vec<DBInfo>, Error>{
+        if let Some(schemas) = self.backup_schemas.as_ref() {
+            return Ok(schemas.databases.clone());
+        }
+
+        // Keys used to scan database records
+        let (lower_bound, upper_bound) = self.get_meta_hash_keys(b"DBs");
+
+        let iter = self.get_cf_iter("write", &lower_bound, &upper_bound)?;
         let mut ret : Vec<DBInfo> = vec![];
         let mut cur_user_key : Vec<u8> = vec![];
 
         for item_res in iter {
-            if let Err(e) = item_res {
-                return Err(Error::StorageNodeError(e.into_string()));
-            }
-
-            let key_data = item_res.as_ref().unwrap().0.as_ref();
+            let (key_data, val_data) = item_res?;
+            let key_data = key_data.as_ref();
 
             let user_key = match Key::truncate_ts_for(key_data) {
                 Ok(k) => k,
@@ -97,10 +1029,17 @@ impl RocksDbStorageNode {
                 continue;
             }
 
-            let val_data = item_res.as_ref().unwrap().1.as_ref();
-            let write_ref = match WriteRef::parse(val_data) {
+            //committed after the read ts, invisible.
+            match Key::decode_ts_from(key_data) {
+                Ok(commit_ts) => if commit_ts > self.read_ts {
+                    continue;
+                },
+                Err(_) => return Err(Error::CorruptedDataBytes("get databases parse ts error.".to_string(), Box::from(key_data))),
+            };
+
+            let write_ref = match WriteRef::parse(val_data.as_ref()) {
                 Ok(r) => r,
-                Err(_) => return Err(Error::CorruptedDataBytes("get databases parse WriteRef error.".to_string(), Box::from(val_data))),
+                Err(_) => return Err(Error::CorruptedDataBytes("get databases parse WriteRef error.".to_string(), val_data)),
             };
 
             match write_ref.write_type {
@@ -127,38 +1066,14 @@ impl RocksDbStorageNode {
 
 
     pub fn get_table_info_by_dbid(&self, db_id : i64) -> Result<Vec<TableInfo>, Error> {
-        let (start_key, end_key) = Self::get_table_info_keys_by_db_id(db_id);
-        let mut readopts = rocksdb::ReadOptions::default();
-        readopts.set_iterate_lower_bound(start_key);
-        readopts.set_iterate_upper_bound(end_key);
-
-        let default_cf = match self.db.cf_handle("default") {
-            Some(cf) => cf,
-            None => return Err(Error::StorageNodeError("cf default not exists.".to_string())),
-        };
+        if let Some(schemas) = self.backup_schemas.as_ref() {
+            return Ok(schemas.tables.iter().filter(|(id, _)| *id == db_id).map(|(_, t)| t.clone()).collect());
+        }
 
-        let iter = self.db.iterator_cf_opt(&default_cf, readopts, rocksdb::IteratorMode::Start);
         let mut table_id_hash : HashMap<i64, TableInfo> = HashMap::new();
         let mut table_id_deleted_time_hash : HashMap<i64, i64> = HashMap::new();
 
-        for item_res in iter {
-            if let Err(e) = item_res {
-                return Err(Error::StorageNodeError(e.into_string()));
-            }
-
-            let v_data = item_res.as_ref().unwrap().1.as_ref();
-            let table_info : TableInfo = match serde_json::from_slice(v_data) {
-                Ok(r) => r,
-                Err(_) => {
-                    return Err(
-                        Error::CorruptedDataString(
-                            "get table info parse JSON str error.".to_string(),
-                            std::str::from_utf8(v_data).unwrap().to_string()
-                        )
-                    );
-                },
-            };
-
+        for table_info in self.get_table_info_versions_by_dbid(db_id)? {
             if table_info.state != crate::tidbtypes::StatePublic {
                 if table_info.state == crate::tidbtypes::StateDeleteOnly {
                     table_id_deleted_time_hash.entry(table_info.id).and_modify(|old_upt_ts| {
@@ -191,7 +1106,233 @@ impl RocksDbStorageNode {
 
     }
 
-    fn get_table_info_keys_by_db_id(db_id :i64) -> (Vec<u8>, Vec<u8>) {
+    //all the versions of the table infos in the database which are still in cf default,
+    //including the ones of the dropped tables.
+    pub fn get_table_info_versions_by_dbid(&self, db_id : i64) -> Result<Vec<TableInfo>, Error> {
+        let (start_key, end_key) = self.get_table_info_keys_by_db_id(db_id);
+
+        //the values in cf default have no commit ts, find out the committed ones from cf write.
+        let committed_start_ts_set = if self.read_ts.is_max() {
+            None
+        } else {
+            Some(self.get_committed_start_ts_set(&start_key, &end_key)?)
+        };
+
+        let iter = self.get_cf_iter("default", &start_key, &end_key)?;
+        let mut ret : Vec<TableInfo> = vec![];
+
+        for item_res in iter {
+            let (key_data, v_data) = item_res?;
+
+            if let Some(start_ts_set) = &committed_start_ts_set {
+                let (user_key, start_ts) = match Key::split_on_ts_for(key_data.as_ref()) {
+                    Ok(r) => r,
+                    Err(_) => return Err(Error::CorruptedDataBytes("get table info parse ts error.".to_string(), key_data)),
+                };
+                if !start_ts_set.contains(&(user_key.to_vec(), start_ts)) {
+                    continue;
+                }
+            }
+
+            let table_info : TableInfo = match serde_json::from_slice(v_data.as_ref()) {
+                Ok(r) => r,
+                Err(_) => {
+                    return Err(
+                        Error::CorruptedDataString(
+                            "get table info parse JSON str error.".to_string(),
+                            String::from_utf8_lossy(v_data.as_ref()).to_string()
+                        )
+                    );
+                },
+            };
+
+            ret.push(table_info);
+        }
+
+        return Ok(ret);
+    }
+
+    //all the versions of the table info committed before or at the read ts, in ascending order of commit ts.
+    pub fn get_table_info_history(&self, db_id : i64, table_id : i64) -> Result<Vec<TableInfoVersion>, Error> {
+        let (lower_bound, upper_bound) = self.get_table_info_key(db_id, table_id);
+        let mut mvcc_iter = self.get_mvcc_iter(&lower_bound, &upper_bound)?;
+        mvcc_iter.set_all_versions(true);
+
+        let mut ret : Vec<TableInfoVersion> = vec![];
+        for mvcc_res in mvcc_iter {
+            let mvcc_value = mvcc_res?;
+            let table_info : TableInfo = match serde_json::from_slice(mvcc_value.value.as_ref()) {
+                Ok(r) => r,
+                Err(_) => {
+                    return Err(
+                        Error::CorruptedDataString(
+                            "get table info parse JSON str error.".to_string(),
+                            String::from_utf8_lossy(mvcc_value.value.as_ref()).to_string()
+                        )
+                    );
+                },
+            };
+            ret.push(TableInfoVersion { commit_ts : mvcc_value.commit_ts.into_inner(), table_info });
+        }
+
+        ret.reverse();
+        return Ok(ret);
+    }
+
+    //the dropped or truncated tables of the database found in the ddl history.
+    pub fn get_dropped_tables(&self, db_id : i64) -> Result<Vec<DroppedTableInfo>, Error> {
+        let table_info_versions = self.get_table_info_versions_by_dbid(db_id)?;
+        let mut ret = vec![];
+
+        for job in self.get_ddl_history_jobs()? {
+            if job.schema_id != db_id || !job.is_finished() {
+                continue;
+            }
+            if job.action_type != tidbtypes::ActionDropTable && job.action_type != tidbtypes::ActionTruncateTable {
+                continue;
+            }
+
+            //the latest public schema of the old table id.
+            let mut table_info = table_info_versions.iter()
+                .filter(|t| t.id == job.table_id && t.state == tidbtypes::StatePublic)
+                .max_by_key(|t| t.update_timestamp)
+                .cloned();
+
+            //the old schema has been cleaned up, the one in the job is the same as it except the ids.
+            if table_info.is_none() {
+                table_info = job.get_table_info().and_then(|mut t| {
+                    //the ids of the partitions are changed by truncating.
+                    if job.action_type == tidbtypes::ActionTruncateTable && t.have_partitions() {
+                        return None;
+                    }
+                    t.id = job.table_id;
+                    t.state = tidbtypes::StatePublic;
+                    Some(t)
+                });
+            }
+
+            ret.push(DroppedTableInfo { job, table_info });
+        }
+
+        return Ok(ret);
+    }
+
+    //the ddl jobs in the history, both the ones in the meta and the ones in mysql.tidb_ddl_history.
+    pub fn get_ddl_history_jobs(&self) -> Result<Vec<DDLJob>, Error> {
+        let mut job_id_hash : HashMap<i64, DDLJob> = HashMap::new();
+
+        let (lower_bound, upper_bound) = self.get_meta_hash_keys(b"DDLJobHistory");
+        for mvcc_res in self.get_mvcc_iter(&lower_bound, &upper_bound)? {
+            let job = Self::parse_ddl_job(mvcc_res?.value.as_ref())?;
+            job_id_hash.insert(job.id, job);
+        }
+
+        let (lower_bound, upper_bound) = self.get_table_data_keys_by_table_id(tidbtypes::DDLHistoryTableID);
+        for mvcc_res in self.get_mvcc_iter(&lower_bound, &upper_bound)? {
+            let mvcc_value = mvcc_res?;
+            if let Some(job_meta) = datum::get_row_column_data(mvcc_value.value.as_ref(), tidbtypes::DDLHistoryJobMetaColumnID)? {
+                let job = Self::parse_ddl_job(&job_meta)?;
+                job_id_hash.insert(job.id, job);
+            }
+        }
+
+        let mut ret : Vec<DDLJob> = job_id_hash.into_values().collect();
+        ret.sort_by_key(|job| job.id);
+        return Ok(ret);
+    }
+
+    fn parse_ddl_job(data : &[u8]) -> Result<DDLJob, Error> {
+        return match serde_json::from_slice(data) {
+            Ok(job) => Ok(job),
+            Err(_) => Err(Error::CorruptedDataString("parse ddl job JSON str error.".to_string(), String::from_utf8_lossy(data).to_string())),
+        };
+    }
+
+    //(user key, start ts) of the puts which are committed before or at the read ts.
+    fn get_committed_start_ts_set(&self, start_key : &[u8], end_key : &[u8]) -> Result<HashSet<(Vec<u8>, TimeStamp)>, Error> {
+        let iter = self.get_cf_iter("write", start_key, end_key)?;
+        let mut ret : HashSet<(Vec<u8>, TimeStamp)> = HashSet::new();
+        for item_res in iter {
+            let (key_data, val_data) = item_res?;
+
+            let (user_key, commit_ts) = match Key::split_on_ts_for(key_data.as_ref()) {
+                Ok(r) => r,
+                Err(_) => return Err(Error::CorruptedDataBytes("get committed start ts parse ts error.".to_string(), key_data)),
+            };
+            if commit_ts > self.read_ts {
+                continue;
+            }
+
+            let write_ref = match WriteRef::parse(val_data.as_ref()) {
+                Ok(r) => r,
+                Err(_) => return Err(Error::CorruptedDataBytes("get committed start ts parse WriteRef error.".to_string(), val_data)),
+            };
+            if write_ref.write_type == WriteType::Put {
+                ret.insert((user_key.to_vec(), write_ref.start_ts));
+            }
+        }
+
+        return Ok(ret);
+    }
+
+    //the ids of the keyspaces which have data in the stores, found by seeking to the next keyspace one by one.
+    pub fn get_keyspaces(&self) -> Result<Vec<u32>, Error> {
+        let mut ret = vec![];
+        let upper_bound = keys::data_key(Key::from_raw(&[KEYSPACE_TXN_MODE_PREFIX + 1]).as_encoded());
+        let mut next_id : u32 = 0;
+        while next_id <= MAX_KEYSPACE_ID {
+            let lower_bound = keys::data_key(Key::from_raw(&get_keyspace_prefix(next_id)).as_encoded());
+            let (key_data, _) = match self.get_region_cf_iter("write", &lower_bound, &upper_bound).next() {
+                Some(res) => res?,
+                None => break,
+            };
+
+            let mut encoded_key_ref = key_data.get(1..).unwrap_or_default();
+            let raw_key = match tikv_util::codec::bytes::decode_bytes(&mut encoded_key_ref, false) {
+                Ok(k) => k,
+                Err(_) => return Err(Error::CorruptedDataBytes("key data decode error.".to_string(), key_data)),
+            };
+            let keyspace_id = match raw_key.get(..4) {
+                Some([_, a, b, c]) => u32::from_be_bytes([0, *a, *b, *c]),
+                _ => return Err(Error::CorruptedDataBytes("the keyspace key is too short.".to_string(), key_data)),
+            };
+            ret.push(keyspace_id);
+            next_id = keyspace_id + 1;
+        }
+        return Ok(ret);
+    }
+
+    //the data key of the raw key of TiDB in the keyspace.
+    fn encode_data_key(&self, raw_key : &[u8]) -> Vec<u8> {
+        let mut key = self.keyspace_prefix.clone();
+        key.extend_from_slice(raw_key);
+        return keys::data_key(Key::from_raw(&key).as_encoded());
+    }
+
+    //the fields of a hash in the meta, m{encoded key}h{encoded field}.
+    fn get_meta_hash_keys(&self, hash_key : &[u8]) -> (Vec<u8>, Vec<u8>) {
+        let mut lower_bound = b"m".to_vec();
+        lower_bound.extend_from_slice(&tikv_util::codec::bytes::encode_bytes(hash_key));
+        lower_bound.extend_from_slice(&(b'h' as u64).to_be_bytes());
+        let mut upper_bound = lower_bound.clone();
+        *upper_bound.last_mut().unwrap() += 1;
+
+        return (self.encode_data_key(&lower_bound), self.encode_data_key(&upper_bound));
+    }
+
+    //the versions of a single table info, m{encoded DB:db_id}h{encoded Table:table_id}.
+    fn get_table_info_key(&self, db_id : i64, table_id : i64) -> (Vec<u8>, Vec<u8>) {
+        let mut lower_bound = b"m".to_vec();
+        lower_bound.extend_from_slice(&tikv_util::codec::bytes::encode_bytes(format!("DB:{}", db_id).as_bytes()));
+        lower_bound.extend_from_slice(&(b'h' as u64).to_be_bytes());
+        lower_bound.extend_from_slice(&tikv_util::codec::bytes::encode_bytes(format!("Table:{}", table_id).as_bytes()));
+        let mut upper_bound = lower_bound.clone();
+        upper_bound.push(0);
+
+        return (self.encode_data_key(&lower_bound), self.encode_data_key(&upper_bound));
+    }
+
+    fn get_table_info_keys_by_db_id(&self, db_id :i64) -> (Vec<u8>, Vec<u8>) {
         let end_db_id = db_id + 1;
         let mut start_key = "mDB:".as_bytes().to_vec();
         let mut end_key = start_key.clone();
@@ -199,18 +1340,66 @@ impl RocksDbStorageNode {
         let mut end_db_id_bytes_vec = end_db_id.to_string().as_bytes().to_vec();
         start_key.append(&mut db_id_bytes_vec);
         end_key.append(&mut end_db_id_bytes_vec);
-        let encoded_start_key = keys::data_key(tikv_util::codec::bytes::encode_bytes(&start_key).as_ref());
-        let encoded_end_key = keys::data_key(tikv_util::codec::bytes::encode_bytes(&end_key).as_ref());
        
-        return (encoded_start_key,  encoded_end_key);
+        return (self.encode_data_key(&start_key), self.encode_data_key(&end_key));
+    }
+
+    pub fn get_index_data_keys_by_index_id(&self, table_id : i64, index_id : i64) -> (Vec<u8>, Vec<u8>) {
+        //t{table_id}_i{index_id}
+        let index_key_lower_bound = tidb_query_datatype::codec::table::encode_index_seek_key(table_id, index_id, &[]);
+        let index_key_upper_bound = tidb_query_datatype::codec::table::encode_index_seek_key(table_id, index_id + 1, &[]);
+
+        return (self.encode_data_key(&index_key_lower_bound), self.encode_data_key(&index_key_upper_bound));
     }
 
-    fn get_table_data_keys_by_table_id(table_id : i64 ) -> (Vec<u8>, Vec<u8>) {
-        let table_id_key_lower_bound = tidb_query_datatype::codec::table::encode_row_key(table_id, 0);
-        let table_id_key_upper_bound = tidb_query_datatype::codec::table::encode_row_key(table_id, i64::MAX);
-        let key_lower_bound = keys::data_key(txn_types::Key::from_raw(table_id_key_lower_bound.as_ref()).as_encoded());
-        let key_upper_bound =  keys::data_key(txn_types::Key::from_raw(table_id_key_upper_bound.as_ref()).as_encoded());
+    pub fn get_table_data_keys_by_table_id(&self, table_id : i64 ) -> (Vec<u8>, Vec<u8>) {
+        //t{table_id}_r, covers both int handles(including negative ones) and common handles.
+        let mut table_id_key_lower_bound = tidb_query_datatype::codec::table::encode_row_key(table_id, 0);
+        table_id_key_lower_bound.truncate(tidb_query_datatype::codec::table::PREFIX_LEN);
+        //t{table_id}_s
+        let mut table_id_key_upper_bound = table_id_key_lower_bound.clone();
+        *table_id_key_upper_bound.last_mut().unwrap() += 1;
 
-        return (key_lower_bound, key_upper_bound);
+        return (self.encode_data_key(&table_id_key_lower_bound), self.encode_data_key(&table_id_key_upper_bound));
     }
-}
\ No newline at end of file
+}
+
+//'x' and the big endian 3 bytes of the keyspace id.
+fn get_keyspace_prefix(keyspace_id : u32) -> [u8; 4] {
+    let id_bytes = keyspace_id.to_be_bytes();
+    return [KEYSPACE_TXN_MODE_PREFIX, id_bytes[1], id_bytes[2], id_bytes[3]];
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use txn_types::Write;
+
+    #[test]
+    fn test_titan_value_in_memtable() {
+        let dir = tempfile::tempdir().unwrap();
+        let key = Key::from_raw(b"k");
+        {
+            let mut opts = Options::default();
+            opts.create_if_missing(true);
+            opts.create_missing_column_families(true);
+            let db = DB::open_cf(&opts, dir.path(), STORE_CFS.into_iter()).unwrap();
+            //not flushed, the records are only in the WAL.
+            let default_key = keys::data_key(key.clone().append_ts(10.into()).as_encoded());
+            db.put_cf(db.cf_handle("default").unwrap(), default_key, b"v").unwrap();
+            let write_key = keys::data_key(key.clone().append_ts(20.into()).as_encoded());
+            let write = Write::new(WriteType::Put, 10.into(), None);
+            db.put_cf(db.cf_handle("write").unwrap(), write_key, write.as_ref().to_bytes()).unwrap();
+        }
+        std::fs::create_dir(dir.path().join(titan::TITAN_DIR_NAME)).unwrap();
+
+        let rocksdb_node = RocksDbStorageNode::new(&[dir.path().display().to_string()]).unwrap();
+        let lower_bound = keys::data_key(key.as_encoded());
+        let mut mvcc_iter = rocksdb_node.get_mvcc_iter(&lower_bound, keys::DATA_MAX_KEY).unwrap();
+        let value = mvcc_iter.next().unwrap().unwrap();
+        assert_eq!(value.key.as_ref(), b"k");
+        assert_eq!(value.value.as_ref(), b"v");
+        assert_eq!(value.cf, "default");
+        assert!(mvcc_iter.next().is_none());
+    }
+}
@@ -0,0 +1,99 @@
+use txn_types::{Key, TimeStamp, WriteRef, WriteType};
+
+use crate::{errors::Error, storagenode::RocksDbStorageNode, tidbtypes::TableInfo};
+
+//the statistics of the data of a table, for sizing the exports.
+#[derive(Default)]
+pub struct TableStats {
+    //the rows visible at the read ts.
+    pub row_num : usize,
+    //the records in cf write of all types and commit ts.
+    pub version_num : usize,
+    pub put_num : usize,
+    pub delete_num : usize,
+    pub rollback_num : usize,
+    //the records of SELECT FOR UPDATE, with the write type Lock.
+    pub lock_record_num : usize,
+    //the pending locks in cf lock.
+    pub lock_num : usize,
+    //the logical bytes, the values in the blob files of titan are counted by their sizes instead of the blob indexes.
+    pub default_bytes : u64,
+    pub write_bytes : u64,
+    pub lock_bytes : u64,
+    //the encoded handles of the first and the last visible rows.
+    pub min_handle : Option<Box<[u8]>>,
+    pub max_handle : Option<Box<[u8]>>,
+    //of the puts and deletes committed before or at the read ts.
+    pub oldest_commit_ts : Option<TimeStamp>,
+    pub newest_commit_ts : Option<TimeStamp>,
+    //the puts and deletes committed after the read ts, which are invisible to the rows.
+    pub after_read_ts_num : usize,
+}
+
+//the visible rows are counted by the TableDataIterator, and the records of all the versions
+//are counted by scanning the cfs in the data range of the table.
+pub fn collect_table_stats(rocksdb_node : &RocksDbStorageNode, table_info : &TableInfo) -> Result<TableStats, Error> {
+    let mut stats = TableStats::default();
+
+    //the rows are in the order of handle.
+    for row_data_res in rocksdb_node.get_table_data_iter(table_info)? {
+        let row_data = row_data_res?;
+        if stats.min_handle.is_none() {
+            stats.min_handle = Some(Box::from(row_data.get_handle()));
+        }
+        stats.max_handle = Some(Box::from(row_data.get_handle()));
+        stats.row_num += 1;
+    }
+
+    let read_ts = rocksdb_node.get_read_ts();
+    let (lower_bound, upper_bound) = rocksdb_node.get_table_data_keys_by_table_id(table_info.id);
+    for item_res in rocksdb_node.get_region_cf_iter("write", &lower_bound, &upper_bound) {
+        let (key_data, val_data) = item_res?;
+        stats.write_bytes += (key_data.len() + val_data.len()) as u64;
+        stats.version_num += 1;
+
+        let commit_ts = match Key::decode_ts_from(key_data.as_ref()) {
+            Ok(ts) => ts,
+            Err(_) => return Err(Error::CorruptedDataBytes("cf write key decode ts error.".to_string(), key_data)),
+        };
+        let write_ref = match WriteRef::parse(val_data.as_ref()) {
+            Ok(r) => r,
+            Err(_) => return Err(Error::CorruptedDataBytes("parse WriteRef error.".to_string(), val_data)),
+        };
+        match write_ref.write_type {
+            WriteType::Put => stats.put_num += 1,
+            WriteType::Delete => stats.delete_num += 1,
+            WriteType::Rollback => {
+                stats.rollback_num += 1;
+                continue;
+            },
+            WriteType::Lock => {
+                stats.lock_record_num += 1;
+                continue;
+            },
+        }
+        if commit_ts > read_ts {
+            stats.after_read_ts_num += 1;
+            continue;
+        }
+        if stats.oldest_commit_ts.map_or(true, |ts| commit_ts < ts) {
+            stats.oldest_commit_ts = Some(commit_ts);
+        }
+        if stats.newest_commit_ts.map_or(true, |ts| commit_ts > ts) {
+            stats.newest_commit_ts = Some(commit_ts);
+        }
+    }
+
+    for item_res in rocksdb_node.get_region_cf_iter("default", &lower_bound, &upper_bound) {
+        let (key_data, val_data) = item_res?;
+        stats.default_bytes += (key_data.len() + val_data.len()) as u64;
+    }
+
+    for item_res in rocksdb_node.get_region_cf_iter("lock", &lower_bound, &upper_bound) {
+        let (key_data, val_data) = item_res?;
+        stats.lock_bytes += (key_data.len() + val_data.len()) as u64;
+        stats.lock_num += 1;
+    }
+
+    return Ok(stats);
+}
@@ -28,8 +28,25 @@ pub const IndexTypeBtree : SchemaState = 1;
 pub const IndexTypeHash : SchemaState = 2;
 pub const IndexTypeRtree : SchemaState = 3;
 
+pub type ActionType = u8;
+
+//from tidb definition
+pub const ActionDropTable : ActionType = 4;
+pub const ActionTruncateTable : ActionType = 11;
+
+pub type JobState = u8;
+
+//from tidb definition
+pub const JobStateDone : JobState = 4;
+pub const JobStateSynced : JobState = 6;
+
+//the table id of mysql.tidb_ddl_history, the ddl jobs are moved into it since the concurrent ddl.
+pub const DDLHistoryTableID : i64 = (1 << 48) - 1 - 3;
+//the column id of job_meta in mysql.tidb_ddl_history
+pub const DDLHistoryJobMetaColumnID : i64 = 2;
+
 //from tidb definition
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Clone)]
 pub struct DBInfo {
     pub id: i64,
     pub db_name: CIStr,
@@ -81,6 +98,26 @@ impl TableInfo {
 
         return partition_table_infos;
     }
+
+    pub fn get_primary_index(&self) -> Option<&IndexInfo> {
+        return self.index_info.iter().find(|idx| idx.is_primary);
+    }
+
+    //the columns encoded in the common handle, in the order of the primary key.
+    pub fn get_common_handle_cols(&self) -> Vec<&ColumnInfo> {
+        if !self.is_common_handle {
+            return Vec::default();
+        }
+
+        let primary_index = match self.get_primary_index() {
+            Some(idx) => idx,
+            None => return Vec::default(),
+        };
+
+        return primary_index.idx_cols.iter()
+            .filter_map(|idx_col| self.cols.get(idx_col.offset as usize))
+            .collect();
+    }
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -135,28 +172,114 @@ pub struct ColumnInfo {
     pub comment : String,
     pub hidden : bool,
     pub version : u64,
+
+    //the default value when the column is added, for the rows written before that.
+    #[serde(default)]
+    pub origin_default : Option<serde_json::Value>,
+    //the origin default of the bit column, []byte encoded by base64.
+    #[serde(default)]
+    pub origin_default_bit : Option<String>,
+}
+
+impl ColumnInfo {
+    //the origin default as the string representation, None for null.
+    pub fn get_origin_default(&self) -> Option<String> {
+        return match self.origin_default.as_ref()? {
+            serde_json::Value::Null => None,
+            serde_json::Value::String(s) => Some(s.clone()),
+            v => Some(v.to_string()),
+        };
+    }
+
+    //the standard base64 used by the json encoding of golang for []byte.
+    pub fn get_origin_default_bit(&self) -> Option<Vec<u8>> {
+        return base64::decode(self.origin_default_bit.as_ref()?).ok();
+    }
 }
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct IndexColumn {
-    name : CIStr,
-    offset : i32,
-    length : i32,
+    pub name : CIStr,
+    pub offset : i32,
+    pub length : i32,
 }
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct IndexInfo {
-    id : i64,
-    idx_name : CIStr,
-    tbl_name : CIStr,
-    idx_cols : Vec<IndexColumn>,
-    state : SchemaState,
-    comment : String,
-    index_type : IndexType,
-    is_unique : bool,
-    is_primary : bool,
-    is_invisible : bool,
-    is_global : bool,
+    pub id : i64,
+    pub idx_name : CIStr,
+    pub tbl_name : CIStr,
+    pub idx_cols : Vec<IndexColumn>,
+    pub state : SchemaState,
+    pub comment : String,
+    pub index_type : IndexType,
+    pub is_unique : bool,
+    pub is_primary : bool,
+    pub is_invisible : bool,
+    pub is_global : bool,
+}
+
+//from tidb definition
+#[derive(Debug, Clone, Deserialize)]
+pub struct DDLJob {
+    pub id : i64,
+    #[serde(rename = "type")]
+    pub action_type : ActionType,
+    pub schema_id : i64,
+    pub table_id : i64,
+    #[serde(default)]
+    pub schema_name : String,
+    #[serde(default)]
+    pub table_name : String,
+    pub state : JobState,
+    #[serde(default)]
+    pub query : String,
+    pub start_ts : u64,
+    pub binlog : Option<HistoryInfo>,
+}
+
+impl DDLJob {
+    pub fn is_finished(&self) -> bool {
+        return self.state == JobStateDone || self.state == JobStateSynced;
+    }
+
+    //the table info after the job is finished, it's the new table for truncating.
+    pub fn get_table_info(&self) -> Option<TableInfo> {
+        let table_info_value = self.binlog.as_ref()?.table_info.as_ref()?;
+        return serde_json::from_value(table_info_value.clone()).ok();
+    }
+
+    //the ts when the job is finished, or the start ts for the old versions of tidb.
+    pub fn get_finished_ts(&self) -> u64 {
+        return match self.binlog.as_ref() {
+            Some(binlog) if binlog.finished_ts > 0 => binlog.finished_ts,
+            _ => self.start_ts,
+        };
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct HistoryInfo {
+    #[serde(rename = "SchemaVersion", default)]
+    pub schema_version : i64,
+    #[serde(rename = "TableInfo", default)]
+    pub table_info : Option<serde_json::Value>,
+    #[serde(rename = "FinishedTS", default)]
+    pub finished_ts : u64,
+}
+
+//a table dropped or truncated, its data is still there before being cleaned up by the GC.
+pub struct DroppedTableInfo {
+    pub job : DDLJob,
+    //the schema before the table is dropped, with the old table id.
+    pub table_info : Option<TableInfo>,
+}
+
+//a version of the table info in the meta.
+#[derive(Debug, Clone)]
+pub struct TableInfoVersion {
+    pub commit_ts : u64,
+    pub table_info : TableInfo,
 }
 
 fn deserialize_null_default<'de, D, T>(deserializer: D) -> Result<T, D::Error>
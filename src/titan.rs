@@ -0,0 +1,143 @@
+use std::{collections::HashMap, fs::File, io::{Read, Seek, SeekFrom}, path::{Path, PathBuf}};
+
+use crate::{errors::Error, kviter::KvPair, sst::{SstMergeIterator, VALUE_TYPE_BLOB_INDEX, block::{self, read_fixed32, read_varint64, read_length_prefixed_slice}}};
+
+//from titan definition
+const BLOB_INDEX_TYPE_RECORD : u8 = 1;
+const BLOB_FILE_HEADER_MAGIC_NUMBER : u32 = 0x2be0a614;
+//crc(4) + size(4) + compression(1)
+const BLOB_RECORD_HEADER_SIZE : usize = 9;
+//the blob records are compressed as the format version 2 of rocksdb.
+const BLOB_COMPRESSION_FORMAT_VERSION : u32 = 2;
+
+pub const TITAN_DIR_NAME : &str = "titandb";
+
+//the value stored in the lsm tree instead of the real value.
+pub struct BlobIndex {
+    pub file_number : u64,
+    pub offset : u64,
+    pub size : u64,
+}
+
+impl BlobIndex {
+    pub fn decode(data : &[u8]) -> Result<BlobIndex, Error> {
+        if data.first() != Some(&BLOB_INDEX_TYPE_RECORD) {
+            return Err(Error::CorruptedDataBytes("invalid titan blob index type.".to_string(), Box::from(data)));
+        }
+
+        let mut data_ref = &data[1..];
+        let file_number = read_varint64(&mut data_ref)?;
+        let offset = read_varint64(&mut data_ref)?;
+        let size = read_varint64(&mut data_ref)?;
+        return Ok(BlobIndex { file_number, offset, size });
+    }
+}
+
+//reads the values from the blob files of titan, the opened files are cached.
+pub struct TitanBlobReader {
+    dir : PathBuf,
+    files : HashMap<u64, File>,
+}
+
+impl TitanBlobReader {
+    pub fn new(dir : &Path) -> TitanBlobReader {
+        return TitanBlobReader {
+            dir : dir.to_path_buf(),
+            files : HashMap::new(),
+        };
+    }
+
+    pub fn get_blob_file_path(&self, file_number : u64) -> PathBuf {
+        return self.dir.join(format!("{:06}.blob", file_number));
+    }
+
+    fn get_blob_file(&mut self, file_number : u64, key : &[u8]) -> Result<&mut File, Error> {
+        if !self.files.contains_key(&file_number) {
+            let path = self.get_blob_file_path(file_number);
+            if !path.exists() {
+                return Err(Error::MissingBlobFile(path.display().to_string(), Box::from(key)));
+            }
+
+            let mut file = match File::open(&path) {
+                Ok(f) => f,
+                Err(e) => return Err(Error::IO(format!("open blob file {} error, {}", path.display(), e.to_string()))),
+            };
+            let mut header = [0u8; 4];
+            if let Err(e) = file.read_exact(&mut header) {
+                return Err(Error::IO(format!("read blob file {} header error, {}", path.display(), e.to_string())));
+            }
+            if read_fixed32(&header)? != BLOB_FILE_HEADER_MAGIC_NUMBER {
+                return Err(Error::StorageNodeError(format!("{} is not a titan blob file.", path.display())));
+            }
+
+            self.files.insert(file_number, file);
+        }
+
+        return Ok(self.files.get_mut(&file_number).unwrap());
+    }
+
+    pub fn get_value(&mut self, key : &[u8], blob_index_data : &[u8]) -> Result<Box<[u8]>, Error> {
+        let blob_index = BlobIndex::decode(blob_index_data)?;
+        let path = self.get_blob_file_path(blob_index.file_number);
+        let file = self.get_blob_file(blob_index.file_number, key)?;
+
+        let mut buf = vec![0u8; blob_index.size as usize];
+        let read_res = file.seek(SeekFrom::Start(blob_index.offset))
+            .and_then(|_| file.read_exact(&mut buf));
+        if let Err(e) = read_res {
+            return Err(Error::IO(format!("read blob record at {} of blob file {} error, {}", blob_index.offset, path.display(), e.to_string())));
+        }
+
+        if buf.len() < BLOB_RECORD_HEADER_SIZE {
+            return Err(Error::CorruptedDataBytes(format!("blob record in {} is too short.", path.display()), Box::from(key)));
+        }
+        let record_size = read_fixed32(&buf[4..8])? as usize;
+        let compression_type = buf[8];
+        let payload = &buf[BLOB_RECORD_HEADER_SIZE..];
+        if payload.len() < record_size {
+            return Err(Error::CorruptedDataBytes(format!("blob record in {} is truncated.", path.display()), Box::from(key)));
+        }
+
+        let record = block::decompress(compression_type, &payload[..record_size], BLOB_COMPRESSION_FORMAT_VERSION)?;
+        let mut record_ref = record.as_slice();
+        let record_key = read_length_prefixed_slice(&mut record_ref)?;
+        let value = read_length_prefixed_slice(&mut record_ref)?;
+        if record_key != key {
+            return Err(Error::CorruptedDataBytes(format!("the key of blob record in {} mismatched.", path.display()), Box::from(key)));
+        }
+
+        return Ok(Box::from(value));
+    }
+}
+
+//resolves the blob indexes of the merged sst files to the real values.
+pub struct TitanKvIterator {
+    inner : SstMergeIterator,
+    blob_reader : TitanBlobReader,
+}
+
+impl TitanKvIterator {
+    pub fn new(inner : SstMergeIterator, blob_reader : TitanBlobReader) -> TitanKvIterator {
+        return TitanKvIterator { inner, blob_reader };
+    }
+}
+
+impl Iterator for TitanKvIterator {
+    type Item = Result<KvPair, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let entry = match self.inner.next()? {
+            Ok(e) => e,
+            Err(e) => return Some(Err(e)),
+        };
+
+        if entry.value_type != VALUE_TYPE_BLOB_INDEX {
+            return Some(Ok((entry.user_key.into_boxed_slice(), entry.value.into_boxed_slice())));
+        }
+
+        return match self.blob_reader.get_value(&entry.user_key, &entry.value) {
+            Ok(v) => Some(Ok((entry.user_key.into_boxed_slice(), v))),
+            Err(e) => Some(Err(e)),
+        };
+    }
+}